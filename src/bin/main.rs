@@ -8,15 +8,14 @@
 use merula::prelude::*;
 
 use merula::{
-    parser::read_from_file,
     mql::parse_mql,
+    diff::diff,
     display
 };
 
 use regex::{Regex, Captures};
 use simplelog::*;
 use log::*;
-use colored::*;
 
 #[allow(unused_imports)]
 use clap::{App, crate_version, Arg, ArgGroup};
@@ -60,14 +59,12 @@ fn init_logger(log_level: u8) {
 // .mql age<18
 // ```
 //
-fn lookup_filter(memos: &Vec<Memo>, filter_name: &str)
+fn lookup_filter(db: &Database, filter_name: &str)
                  -> Result<MemoFilter, String>
 {
     debug!("looking for pre-defined filter '{}'", filter_name);
     
-    let mf = MemoFilter::key_value_equals("mr:filter", filter_name);
-    
-    if let Some(mql_memo) = memos.iter().filter(|&memo| mf.check(memo)).next() {
+    if let Some(mql_memo) = db.get("mr:filter", filter_name) {
         debug!("Resulting filter: {:#?}", mql_memo);
         if let Some(node) = mql_memo.nodes().filter(|&node| node.key == "mql").next() {
             debug!("Resulting node: {:#?}", node);
@@ -85,6 +82,36 @@ fn lookup_filter(memos: &Vec<Memo>, filter_name: &str)
     }
 }
 
+// Read memos from the given .mr files into a database with an
+// inverted index.  Memos that are defined in several files are
+// handled according to `duplicates`.
+fn read_database(input: &[String], duplicates: Duplicates) -> Database {
+    debug!("loading input files {:?}", input);
+    let filenames: Vec<&str> = input.iter().map(|s| s.as_str()).collect();
+    match Database::read_from_files(&filenames, duplicates) {
+        Ok(db) => {
+            debug!("read {} memos", db.len());
+            db.with_index()
+        },
+        Err(msg) => {
+            eprintln!("{}", msg);
+            std::process::exit(1);
+        }
+    }
+}
+
+// Parse the value of the option `--duplicates`, if given.
+fn duplicates_of(matches: &clap::ArgMatches) -> Duplicates {
+    match matches.value_of("duplicates").map(|s| s.parse()) {
+        None => Duplicates::default(),
+        Some(Ok(duplicates)) => duplicates,
+        Some(Err(msg)) => {
+            eprintln!("--duplicates: {}", msg);
+            std::process::exit(1);
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 enum DefaultFilter { All, System, Data }
 
@@ -114,25 +141,26 @@ impl From<DefaultFilter> for MemoFilter {
 }
        
 struct CmdList {
-    input: String,
+    input: Vec<String>,
+    duplicates: Duplicates,
     verbosity: u8,
     default_filter: DefaultFilter,
     filter: Option<String>,
-    mql: Option<String>
+    mql: Option<String>,
+    sort: Option<String>,
+    location: bool
 }
 
-// read memos from .mr file into database
+// read memos from .mr files into database
 fn cmd_list(cmd: CmdList) {
-    debug!("loading input file '{}'", cmd.input);
-    let memos = read_from_file(&cmd.input).unwrap();
-    debug!("read {} memos", memos.len());
+    let db = read_database(&cmd.input, cmd.duplicates);
 
     // setup filter
     let mut memo_filter: MemoFilter = cmd.default_filter.into();
                 
     // check if a pre-defined filter has been supplied
     if let Some(filter_name) = cmd.filter {
-        match lookup_filter(&memos, &filter_name) {
+        match lookup_filter(&db, &filter_name) {
             // TODO: concatenate filter to existing one
             // memo_filter.extend(mf) would AND the condition,
             // while we really want to have an OR, don't we?
@@ -158,10 +186,27 @@ fn cmd_list(cmd: CmdList) {
         }
     }
 
+    let mut result: Vec<&Memo> = db.filter(&memo_filter).collect();
+
+    // sort by the value of the first node with the given key,
+    // memos without such a node come last
+    if let Some(key) = cmd.sort {
+        debug!("sorting by '{}'", key);
+        result.sort_by(|a, b| match (a.get(&key), b.get(&key)) {
+            (Some(a), Some(b)) => a.value.cmp(&b.value),
+            (Some(_), None) => std::cmp::Ordering::Less,
+            (None, Some(_)) => std::cmp::Ordering::Greater,
+            (None, None) => std::cmp::Ordering::Equal
+        });
+    }
+
     println!("verbosity: {}", cmd.verbosity);
-    for memo in memos.iter().filter(|&memo| memo_filter.check(memo)) {
+    for memo in result {
         // always print header
         display::print_header(&memo);
+        if cmd.location {
+            display::print_location(memo.location());
+        }
 
         match cmd.verbosity {
             1 => {
@@ -174,16 +219,8 @@ fn cmd_list(cmd: CmdList) {
                     // that the header node has index 0
                     if idx > 0 {
                         let node = memo.get_by_index(idx).unwrap();
-                        println!("{}{} {}",
-                                 ".".red(),
-                                 node.key.red(),
-                                 node.value.to_string().white());
-                        for (key, value) in node.attrs() {
-                            println!("{}{} {}",
-                                     "+".yellow(),
-                                     key.yellow(),
-                                     value.to_string().white());
-                        }
+                        let location = if cmd.location { memo.location_of(node) } else { None };
+                        display::print_data_node(node, location);
                     }
                 }
                 println!("");
@@ -199,7 +236,8 @@ fn cmd_list(cmd: CmdList) {
 
 
 struct CmdExport {
-    input: String,
+    input: Vec<String>,
+    duplicates: Duplicates,
     #[allow(dead_code)] verbosity: u8,
     default_filter: DefaultFilter,
     filter: Option<String>,
@@ -208,20 +246,11 @@ struct CmdExport {
 }
 
 fn cmd_export(cmd: CmdExport) {
-    debug!("loading input file '{}'", cmd.input);
-    let memos = read_from_file(&cmd.input).unwrap();
-    debug!("read {} memos", memos.len());
+    let db = read_database(&cmd.input, cmd.duplicates);
 
     // check if a pre-defined template has been supplied
     debug!("looking for pre-defined template '{}'", cmd.template);
-    let mut mf = MemoFilter::new();
-    let nf = NodeFilter::default()
-        .with_key(KeyFilter::Equals("mr:template".into()))
-        .with_value(ValueFilter::Equals(cmd.template.to_string()));
-    mf.add(nf);
-    if let Some(tpl_memo) =
-        memos.iter().filter(|&memo| mf.check(memo)).next()
-    {
+    if let Some(tpl_memo) = db.get("mr:template", &cmd.template) {
         //debug!("Resulting template: {:#?}", tpl_memo);
         // get header if available
         if let Some(header) = tpl_memo.get("header") {
@@ -238,7 +267,7 @@ fn cmd_export(cmd: CmdExport) {
 
             // check if a pre-defined filter has been supplied
             if let Some(filter_name) = cmd.filter {
-                match lookup_filter(&memos, &filter_name) {
+                match lookup_filter(&db, &filter_name) {
                     Ok(mf) => memo_filter = mf,
                     Err(msg) => {
                         eprintln!("{}", msg);
@@ -261,7 +290,7 @@ fn cmd_export(cmd: CmdExport) {
                 }
             }
 
-            for memo in memos.iter().filter(|&memo| memo_filter.check(memo)) {
+            for memo in db.filter(&memo_filter) {
                 let result = re.replace_all(tpl, |caps: &Captures| {
                     if let Some(node) = memo.get(&caps[1]) {
                         format!("{}", node.value)
@@ -289,7 +318,8 @@ fn cmd_export(cmd: CmdExport) {
 
 
 struct CmdStats {
-    input: String,
+    input: Vec<String>,
+    duplicates: Duplicates,
     #[allow(dead_code)] verbosity: u8,
     default_filter: DefaultFilter,
     filter: Option<String>,
@@ -297,16 +327,14 @@ struct CmdStats {
 }
 
 fn cmd_stats(cmd: CmdStats) {
-    debug!("loading input file '{}'", cmd.input);
-    let memos = read_from_file(&cmd.input).unwrap();
-    debug!("read {} memos", memos.len());
+    let db = read_database(&cmd.input, cmd.duplicates);
 
     // set default filter
     let mut memo_filter: MemoFilter = cmd.default_filter.into();
                
     // check if a pre-defined filter has been supplied
     if let Some(filter_name) = cmd.filter {
-        match lookup_filter(&memos, &filter_name) {
+        match lookup_filter(&db, &filter_name) {
             Ok(mf) => memo_filter = mf,
             Err(msg) => {
                 eprintln!("{}", msg);
@@ -329,21 +357,42 @@ fn cmd_stats(cmd: CmdStats) {
         }
     }
 
-    let count = memos.iter()
-        .filter(|&memo| memo_filter.check(memo))
+    let count = db.filter(&memo_filter)
         .fold(
             // (#memos, #nodes)
             (0, 0), |acc, m| (acc.0 + 1, acc.1 + m.data_count() + 1)
         );
 
     
-    println!("Statistics for '{}':", cmd.input);
+    println!("Statistics for '{}':", cmd.input.join("', '"));
 
     println!("#Memos = {}", count.0);
     println!("#Nodes = {}", count.1);
 }
 
 
+struct CmdDiff {
+    old: String,
+    new: String
+}
+
+// compare two .mr files or directories and print the changes
+fn cmd_diff(cmd: CmdDiff) {
+    debug!("loading input files '{}' and '{}'", cmd.old, cmd.new);
+    let read = |path: &str| Database::read_from_path(path).unwrap_or_else(|msg| {
+        eprintln!("{}", msg);
+        std::process::exit(1);
+    }).into_memos();
+    let old = read(&cmd.old);
+    let new = read(&cmd.new);
+    debug!("read {} and {} memos", old.len(), new.len());
+
+    let changes = diff(&old, &new);
+    debug!("found {} changed memos", changes.len());
+    display::print_diff(&changes);
+}
+
+
 fn main() {
     let app = App::new("merula")
         .version(crate_version!())
@@ -354,9 +403,12 @@ fn main() {
         .subcommand(
             App::new("list")
                 .about("list memos")
-                .arg("<input> 'sets an input file'")
+                .arg("<input>... 'sets the input files or directories'")
+                .arg("--duplicates=[POLICY] 'memos defined in several files: error (default), last-wins or merge'")
                 .arg("--filter=[FILTER] 'load an mql expression from a pre-defined filter'")
                 .arg("--mql=[MQL] 'sets a mql expression'")
+                .arg("--sort=[KEY] 'sort memos by the value of the given key'")
+                .arg("--location 'print file, line and column of memos and nodes'")
                 .arg("-v --verbose... 'Sets the verbosity level'")
                 .arg("--all 'use all memos (default)'")
                 .arg("--system 'only internal memos (@mr:xxx)'")
@@ -368,7 +420,8 @@ fn main() {
         .subcommand(
             App::new("stats")
                 .about("print memo statistics")
-                .arg("<input> 'sets an input file'")
+                .arg("<input>... 'sets the input files or directories'")
+                .arg("--duplicates=[POLICY] 'memos defined in several files: error (default), last-wins or merge'")
                 .arg("-v --verbose... 'Sets the verbosity level'")
                 .arg("--filter=[FILTER] 'load an mql expression from a pre-defined filter'")
                 .arg("--mql=[MQL] 'sets a mql expression'")
@@ -382,7 +435,8 @@ fn main() {
         .subcommand(
             App::new("export")
                 .about("export data using a template")
-                .arg("<input> 'sets an input file'")
+                .arg("<input>... 'sets the input files or directories'")
+                .arg("--duplicates=[POLICY] 'memos defined in several files: error (default), last-wins or merge'")
                 .arg("--filter=[FILTER] 'load an mql expression from a pre-defined filter'")
                 .arg("--mql=[MQL] 'sets a mql expression'")
                 .arg("-v --verbose... 'Sets the verbosity level'")
@@ -393,6 +447,12 @@ fn main() {
                 .group(ArgGroup::new("default-filter")
                        .args(&["all", "system", "data"])
                        .multiple(false))
+        )
+        .subcommand(
+            App::new("diff")
+                .about("print changes between two files")
+                .arg("<old> 'sets the old input file or directory'")
+                .arg("<new> 'sets the new input file or directory'")
         );
 
     let matches = app.get_matches();
@@ -403,7 +463,9 @@ fn main() {
     
     if let Some(ref matches) = matches.subcommand_matches("list") {
         let cmd = CmdList {
-            input: matches.value_of("input").expect("missing input file").to_string(),
+            input: matches.values_of("input").expect("missing input file")
+                .map(|s| s.to_string()).collect(),
+            duplicates: duplicates_of(matches),
             verbosity: matches.occurrences_of("verbose") as u8,
             default_filter: if matches.is_present("system") {
                 DefaultFilter::System
@@ -413,7 +475,9 @@ fn main() {
                 DefaultFilter::Data
             },
             filter: matches.value_of("filter").map(|s| s.to_string()),
-            mql: matches.value_of("mql").map(|s| s.to_string())
+            mql: matches.value_of("mql").map(|s| s.to_string()),
+            sort: matches.value_of("sort").map(|s| s.to_string()),
+            location: matches.is_present("location")
         };
 
         cmd_list(cmd);
@@ -423,7 +487,9 @@ fn main() {
     
     if let Some(ref matches) = matches.subcommand_matches("export") {
         let cmd = CmdExport {
-            input: matches.value_of("input").expect("missing input file").to_string(),
+            input: matches.values_of("input").expect("missing input file")
+                .map(|s| s.to_string()).collect(),
+            duplicates: duplicates_of(matches),
             verbosity: matches.occurrences_of("verbose") as u8,
             default_filter: if matches.is_present("system") {
                 DefaultFilter::System
@@ -445,7 +511,9 @@ fn main() {
     
     if let Some(ref matches) = matches.subcommand_matches("stats") {
         let cmd = CmdStats {
-            input: matches.value_of("input").expect("missing input file").to_string(),
+            input: matches.values_of("input").expect("missing input file")
+                .map(|s| s.to_string()).collect(),
+            duplicates: duplicates_of(matches),
             verbosity: matches.occurrences_of("verbose") as u8,
             default_filter: if matches.is_present("system") {
                 DefaultFilter::System
@@ -460,4 +528,15 @@ fn main() {
 
         cmd_stats(cmd);
     }
+
+    // --- SUBCOMMAND `diff` ---
+
+    if let Some(matches) = matches.subcommand_matches("diff") {
+        let cmd = CmdDiff {
+            old: matches.value_of("old").expect("missing old input file").to_string(),
+            new: matches.value_of("new").expect("missing new input file").to_string()
+        };
+
+        cmd_diff(cmd);
+    }
 }
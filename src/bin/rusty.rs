@@ -42,7 +42,6 @@ use std::borrow::Cow::{self, Borrowed, Owned};
 use merula::prelude::*;
 
 use merula::{
-    parser::read_from_file,
     mql::parse_mql,
     display,
 };
@@ -140,7 +139,7 @@ impl ConditionalEventHandler for TabEventHandler {
 
 #[derive(Default)]
 struct AppState {
-    memos: Vec<Memo>,
+    db: Database,
 }
 
 
@@ -170,7 +169,7 @@ fn main() {
     loop {
         let prompt = format!(
             "{len} {prompt} ",
-            len = state.memos.len(),
+            len = state.db.len(),
             prompt = "»".bold()
         );
         let readline = rl.readline(&prompt);
@@ -190,7 +189,7 @@ fn main() {
                         println!("commands:");
                         println!("h | help         print this help");
                         println!("q | quit         quit");
-                        println!("load file        load .mr data file");
+                        println!("load file        load .mr data file or directory");
                         println!("ls               list all loaded memos");
                         println!("clear            clear memo database");
                         println!("v | view <n>     view memo with given id")
@@ -199,17 +198,18 @@ fn main() {
                         println!("load file");
                         for arg in args {
                             print!("reading from file '{}'...", arg);
-                            if let Ok(new_memos) = read_from_file(&arg) {
-                                println!("{} memos", new_memos.len());
-                                state.memos.extend(new_memos);
-                            } else {
-                                println!("failed!");
+                            match Database::read_from_path(&arg) {
+                                Ok(new_db) => {
+                                    println!("{} memos", new_db.len());
+                                    state.db.extend(new_db.into_memos());
+                                },
+                                Err(msg) => println!("failed: {}", msg)
                             }
                            
                         };
                     },
                     Some("ls") => {
-                        for (n, memo) in state.memos.iter().enumerate() {
+                        for (n, memo) in state.db.iter().enumerate() {
                             //.filter(|&memo| memo_filter.check(memo)) {
                             print!(
                                 "{}",
@@ -222,7 +222,7 @@ fn main() {
                         if let Some(n) = args.next() {
                             if let Ok(n) = n.parse::<usize>() {
                                 //println!("view #{}", n);
-                                if let Some(memo) = state.memos.get(n) {
+                                if let Some(memo) = state.db.get_by_index(n) {
                                     display::print_header(&memo);
                                     display::print_data_nodes(&memo);
                                 } else {
@@ -234,7 +234,7 @@ fn main() {
                         }
                     },
                     Some("clear") => {
-                        state.memos.clear();
+                        state.db.clear();
                         println!("all memos removed");
                     }
                     None => {}, // ignore empty input
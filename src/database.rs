@@ -0,0 +1,609 @@
+//! A Database owns a list of memos and keeps indices to look them up.
+//!
+//! Memos keep the order in which they were added.  They are indexed
+//! by collection, by collection and title and by id (see
+//! `Memo::id`).  If several memos share the same collection and
+//! title or the same id, lookups return the first one.
+//!
+//! A Database may be read from several files, e.g. a schema file
+//! with prototypes and filters plus a data file.  Each memo remembers
+//! the file it was read from, see `Memo::path`.  If a memo with the
+//! same collection and title is defined in more than one file, the
+//! `Duplicates` policy decides what happens:
+//!
+//! - `error` (default): reading fails and nothing is added,
+//! - `last-wins`: the memo of the later file replaces the earlier
+//!   one, but keeps its position,
+//! - `merge`: the data nodes and header attributes of the later memo
+//!   are added to the earlier one, unless it contains equal ones.
+//!   The merged memo keeps the path of the earlier file, and the
+//!   added nodes have no location.
+//!
+//! The policy applies only between files, memos with the same
+//! collection and title within one file are all kept as before.
+//! A file that is included by several files (`@mr:include`) is only
+//! added once, and the `mr:include` memos themselves are never
+//! duplicates.
+//!
+//! Optionally, the Database keeps an inverted index of keys and
+//! values (see `index.rs`) to speed up filtering large databases.
+//!
+//! ```
+//! use merula::prelude::*;
+//! let db = Database::from(vec!(
+//!     Memo::new("element", "H").with(("number", 1)),
+//!     Memo::new("element", "He").with(("number", 2))
+//! ));
+//! assert_eq!(db.get("element", "He").unwrap().get("number").unwrap().value, Value::from(2));
+//! assert_eq!(db.collections(), vec!("element"));
+//! ```
+//!
+
+use std::collections::HashMap;
+use std::fs;
+use std::str::FromStr;
+
+use crate::{Memo, Key, Value};
+use crate::memo::MemoId;
+use crate::filter::MemoFilter;
+use crate::index::Index;
+use crate::parser;
+
+/// Policy for memos with the same collection and title that are
+/// added from different files, see the module documentation.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Duplicates { #[default] Error, LastWins, Merge }
+
+impl FromStr for Duplicates {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "error" => Ok(Duplicates::Error),
+            "last-wins" => Ok(Duplicates::LastWins),
+            "merge" => Ok(Duplicates::Merge),
+            _ => Err("unknown policy, expected 'error', 'last-wins' or 'merge'")
+        }
+    }
+}
+
+// Returns true if both memos were read from the same file, e.g. from
+// a file that is included by several files.
+fn same_file(memo: &Memo, other: &Memo) -> bool {
+    match (memo.path(), other.path()) {
+        (Some(a), Some(b)) => a == b || match fs::canonicalize(a) {
+            Ok(a) => fs::canonicalize(b).is_ok_and(|b| a == b),
+            Err(_) => false
+        },
+        _ => false
+    }
+}
+
+// Adds the header attributes and data nodes of `other` to `memo`,
+// unless `memo` already contains equal ones.
+fn merge_memo(memo: &mut Memo, other: Memo) {
+    let mut nodes = other.nodes().cloned();
+    if let Some(header) = nodes.next() {
+        for (key, value) in header.attrs() {
+            if !memo.header().get_attr_vec(key.as_str()).contains(&value) {
+                memo.header_mut().push_attr(key.as_str(), value.clone());
+            }
+        }
+    }
+    for mut node in nodes {
+        if !memo.data().any(|other| other == &node) {
+            // the location would refer to the other file
+            node.location = None;
+            memo.push(node);
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Database {
+    memos: Vec<Memo>,
+    by_collection: HashMap<Key, Vec<usize>>,
+    by_title: HashMap<(Key, String), usize>,
+    by_id: HashMap<MemoId, usize>,
+    index: Option<Index> // used by `filter`, if present
+}
+
+impl Database {
+    /// Constructs a new, empty Database.
+    pub fn new() -> Self {
+        Database::default()
+    }
+
+    /// Reads the memos from the given `.mr` file, see
+    /// `parser::read_from_file`.  Fails with a message naming the
+    /// file, see `read_from_files`.
+    pub fn read_from_file(filename: &str) -> Result<Database, String> {
+        let mut memos = parser::read_memos_from_file(filename)?;
+        parser::resolve_memos(&mut memos);
+        Ok(Database::from(memos))
+    }
+
+    /// Reads the memos from the given `.mr` file or directory, see
+    /// `parser::read_from_path`.  Fails with a message naming the
+    /// file, see `read_from_files`.
+    pub fn read_from_path(path: &str) -> Result<Database, String> {
+        let mut memos: Vec<Memo> = parser::read_memos_from_path(path)?
+            .into_iter()
+            .flatten()
+            .collect();
+        parser::resolve_memos(&mut memos);
+        Ok(Database::from(memos))
+    }
+
+    /// Reads the memos from the given `.mr` files, in this order.  A
+    /// directory stands for all `.mr` files below it, see
+    /// `parser::read_from_path`.
+    /// Memos defined in several files are handled according to the
+    /// given policy.  Prototypes are applied once all files are read,
+    /// so a prototype may be defined in another file than the memos
+    /// extending it.
+    pub fn read_from_files(filenames: &[&str], duplicates: Duplicates) -> Result<Database, String> {
+        let mut db = Database::new();
+        for filename in filenames {
+            let files = parser::read_memos_from_path(filename)?;
+            for memos in files {
+                db.merge(memos, duplicates)?;
+            }
+        }
+        let mut memos = db.into_memos();
+        parser::resolve_memos(&mut memos);
+        Ok(Database::from(memos))
+    }
+
+    /// Builds the inverted index and returns the instance of the
+    /// Database.  The index is kept up to date when memos are added.
+    pub fn with_index(mut self) -> Self {
+        self.build_index();
+        self
+    }
+
+    /// Builds the inverted index, see `with_index`.
+    pub fn build_index(&mut self) {
+        self.index = Some(Index::new(&self.memos));
+    }
+
+    /// Returns true if the Database keeps an inverted index.
+    pub fn has_index(&self) -> bool {
+        self.index.is_some()
+    }
+
+    /// Adds the given memo and updates the indices.
+    pub fn push(&mut self, memo: Memo) {
+        if let Some(index) = self.index.as_mut() {
+            index.push(&memo);
+        }
+        let index = self.memos.len();
+        let collection = memo.collection();
+        self.by_collection.entry(collection.clone()).or_default().push(index);
+        self.by_title.entry((collection, memo.title())).or_insert(index);
+        self.by_id.entry(memo.id()).or_insert(index);
+        self.memos.push(memo);
+    }
+
+    /// Adds the memos of another file, e.g. the result of
+    /// `parser::read_from_file`.  Memos whose collection and title
+    /// already exist in the Database are handled according to the
+    /// given policy.  With `Duplicates::Error`, nothing is added if
+    /// there is any such memo.
+    pub fn merge(&mut self, memos: Vec<Memo>, duplicates: Duplicates) -> Result<(), String> {
+        // only memos that were added before are duplicates, except
+        // for include memos
+        let len = self.memos.len();
+        let existing = |db: &Database, memo: &Memo| db.by_title
+            .get(&(memo.collection(), memo.title()))
+            .copied()
+            .filter(|&index| index < len && memo.collection() != "mr:include");
+
+        // the memos of a file that has already been included are skipped
+        let memos: Vec<Memo> = memos.into_iter()
+            .filter(|memo| match existing(self, memo) {
+                Some(index) => !same_file(&self.memos[index], memo),
+                None => true
+            })
+            .collect();
+
+        if duplicates == Duplicates::Error {
+            if let Some(memo) = memos.iter().find(|memo| existing(self, memo).is_some()) {
+                let path = |memo: &Memo| memo.path()
+                    .map(|path| path.display().to_string())
+                    .unwrap_or_default();
+                let index = existing(self, memo).unwrap();
+                return Err(format!("@{} {} is defined in '{}' and '{}'",
+                                   memo.collection(), memo.title(),
+                                   path(&self.memos[index]), path(memo)));
+            }
+        }
+
+        for memo in memos {
+            match (existing(self, &memo), duplicates) {
+                (Some(index), Duplicates::LastWins) =>
+                    self.update(index, |old| *old = memo),
+                (Some(index), Duplicates::Merge) =>
+                    self.update(index, |old| merge_memo(old, memo)),
+                _ => self.push(memo)
+            }
+        }
+        Ok(())
+    }
+
+    // Changes the memo at position `index` and updates its entries in
+    // the indices.  Collection and title must not be changed.
+    fn update<F: FnOnce(&mut Memo)>(&mut self, index: usize, f: F) {
+        if let Some(inverted) = self.index.as_mut() {
+            inverted.remove_entries(index, &self.memos[index]);
+        }
+        let old_id = self.memos[index].id();
+        f(&mut self.memos[index]);
+        let memo = &self.memos[index];
+        if let Some(inverted) = self.index.as_mut() {
+            inverted.add_entries(index, memo);
+        }
+
+        // an explicit id may have been changed
+        let id = memo.id();
+        if id != old_id {
+            if self.by_id.get(&old_id) == Some(&index) {
+                self.by_id.remove(&old_id);
+            }
+            let first = self.by_id.entry(id).or_insert(index);
+            *first = (*first).min(index);
+        }
+    }
+
+    /// Removes all memos.  An inverted index is kept, but emptied.
+    pub fn clear(&mut self) {
+        self.memos.clear();
+        self.reindex();
+    }
+
+    // Rebuilds all indices from scratch.
+    fn reindex(&mut self) {
+        let has_index = self.has_index();
+        let memos = std::mem::take(&mut self.memos);
+        *self = Database::from(memos);
+        if has_index {
+            self.build_index();
+        }
+    }
+
+    /// Returns the number of memos.
+    pub fn len(&self) -> usize {
+        self.memos.len()
+    }
+
+    /// Returns true if the Database contains no memos.
+    pub fn is_empty(&self) -> bool {
+        self.memos.is_empty()
+    }
+
+    /// Returns all memos in the order they were added.
+    pub fn memos(&self) -> &[Memo] {
+        &self.memos
+    }
+
+    /// Returns iterator to all memos.
+    pub fn iter(&self) -> std::slice::Iter<'_, Memo> {
+        self.memos.iter()
+    }
+
+    /// Returns iterator to all memos that match the given filter.
+    /// With an inverted index, only the candidates are checked.
+    pub fn filter<'a>(&'a self, filter: &'a MemoFilter) -> Box<dyn Iterator<Item=&'a Memo> + 'a> {
+        match &self.index {
+            Some(index) => Box::new(filter.filter_indexed(&self.memos, index)),
+            None => Box::new(self.memos.iter().filter(move |&memo| filter.check(memo)))
+        }
+    }
+
+    /// Returns the memo with the given collection and title.
+    pub fn get(&self, collection: &str, title: &str) -> Option<&Memo> {
+        self.by_title.get(&(collection.into(), title.into()))
+            .map(|&index| &self.memos[index])
+    }
+
+    /// Returns the memo with the given id.
+    pub fn get_by_id(&self, id: &str) -> Option<&Memo> {
+        self.by_id.get(id).map(|&index| &self.memos[index])
+    }
+
+    /// Returns the memo with the given position.
+    pub fn get_by_index(&self, index: usize) -> Option<&Memo> {
+        self.memos.get(index)
+    }
+
+    /// Returns iterator to all memos of the given collection.
+    pub fn collection<'a>(&'a self, collection: &str) -> impl Iterator<Item=&'a Memo> {
+        self.by_collection.get(collection)
+            .map(|indices| indices.as_slice())
+            .unwrap_or_default()
+            .iter()
+            .map(move |&index| &self.memos[index])
+    }
+
+    /// Returns the names of all collections in alphabetical order.
+    pub fn collections(&self) -> Vec<&str> {
+        let mut collections: Vec<&str> = self.by_collection.keys()
+            .map(|key| key.as_str())
+            .collect();
+        collections.sort_unstable();
+        collections
+    }
+
+    /// Returns the keys of all data nodes in alphabetical order.
+    pub fn keys(&self) -> Vec<&str> {
+        let mut keys: Vec<&str> = self.memos.iter()
+            .flat_map(|memo| memo.data())
+            .map(|node| node.key.as_str())
+            .collect();
+        keys.sort_unstable();
+        keys.dedup();
+        keys
+    }
+
+    /// Returns the memo the given value refers to, see
+    /// `reference::resolve`.
+    pub fn resolve(&self, value: &Value) -> Option<&Memo> {
+        match value {
+            Value::Ref(collection, title) => self.get(collection, title),
+            Value::RefById(id) => self.get_by_id(id),
+            _ => None
+        }
+    }
+
+    /// Returns the memos, consuming the Database.
+    pub fn into_memos(self) -> Vec<Memo> {
+        self.memos
+    }
+}
+
+impl From<Vec<Memo>> for Database {
+    fn from(memos: Vec<Memo>) -> Database {
+        let mut db = Database::new();
+        db.extend(memos);
+        db
+    }
+}
+
+impl Extend<Memo> for Database {
+    fn extend<I: IntoIterator<Item=Memo>>(&mut self, memos: I) {
+        for memo in memos {
+            self.push(memo);
+        }
+    }
+}
+
+impl<'a> IntoIterator for &'a Database {
+    type Item = &'a Memo;
+    type IntoIter = std::slice::Iter<'a, Memo>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.memos.iter()
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sample::setup_memos;
+    use crate::mql::parse_mql;
+    use crate::memo::generate_id;
+    use std::path::{Path, PathBuf};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    // A directory with the given files in the temporary directory,
+    // which is removed when dropped.  Each instance gets its own
+    // directory, so tests can run in parallel.
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(files: &[(&str, &str)]) -> TempDir {
+            static COUNT: AtomicUsize = AtomicUsize::new(0);
+            let dir = std::env::temp_dir().join(format!(
+                "merula-database-{}-{}", std::process::id(), COUNT.fetch_add(1, Ordering::SeqCst)));
+            let _ = std::fs::remove_dir_all(&dir);
+            for (file, content) in files {
+                let path = dir.join(file);
+                std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+                std::fs::write(path, content).unwrap();
+            }
+            TempDir(dir)
+        }
+
+        fn path(&self) -> &Path {
+            &self.0
+        }
+
+        // Returns the given file or directory below the directory as string.
+        fn join(&self, file: &str) -> String {
+            self.0.join(file).to_str().unwrap().to_string()
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[test]
+    fn lookup() {
+        let mut db = Database::from(setup_memos());
+        assert_eq!(db.len(), setup_memos().len());
+        let book = db.get("book", "The Lord of the Rings").unwrap();
+        let book_id = book.id();
+        assert_eq!(db.get_by_id(&book_id).unwrap().title(), book.title());
+        let author = db.resolve(&book.get("author").unwrap().value).unwrap();
+        assert_eq!(author.title(), "J.R.R. Tolkien");
+        assert!(db.get("book", "The Silmarillion").is_none());
+        assert!(db.get_by_id("unknown").is_none());
+
+        db.push(Memo::new("book", "The Lord of the Rings").with_attr("id", "lotr"));
+        // the first memo with this title is kept
+        assert_eq!(db.get("book", "The Lord of the Rings").unwrap().id(), book_id);
+        assert_eq!(db.get_by_id("lotr").unwrap().header().get_attr("id"),
+                   Some(&Value::from("lotr")));
+
+        db.clear();
+        assert!(db.is_empty());
+        assert!(db.get("author", "J.R.R. Tolkien").is_none());
+    }
+
+    #[test]
+    fn collections_and_keys() {
+        let db = Database::from(vec!(
+            Memo::new("element", "H").with(("number", 1)),
+            Memo::new("mr:filter", "noble gases").with(("mql", "group=18")),
+            Memo::new("element", "He").with(("number", 2)).with(("group", 18))
+        ));
+        assert_eq!(db.collections(), vec!("element", "mr:filter"));
+        assert_eq!(db.keys(), vec!("group", "mql", "number"));
+        let titles: Vec<String> = db.collection("element").map(|memo| memo.title()).collect();
+        assert_eq!(titles, vec!("H", "He"));
+        assert_eq!(db.collection("book").count(), 0);
+
+        let filter = parse_mql("group=18").unwrap();
+        let titles: Vec<String> = db.filter(&filter).map(|memo| memo.title()).collect();
+        assert_eq!(titles, vec!("He"));
+        assert_eq!((&db).into_iter().count(), 3);
+
+        let mut db = db.with_index();
+        assert!(db.has_index());
+        db.push(Memo::new("element", "Ne").with(("group", 18)));
+        let titles: Vec<String> = db.filter(&filter).map(|memo| memo.title()).collect();
+        assert_eq!(titles, vec!("He", "Ne"));
+        db.clear();
+        assert!(db.has_index());
+        assert_eq!(db.filter(&filter).count(), 0);
+    }
+
+    fn sources() -> (Vec<Memo>, Vec<Memo>) {
+        let mut schema = vec!(
+            Memo::new("mr:filter", "metals").with(("mql", "metal=yes")),
+            Memo::new("element", "Fe").with(("number", 26)).with(("metal", "yes"))
+        );
+        let mut data = vec!(
+            Memo::new("element", "Fe").with(("number", 26)).with(("amu", 55.845)),
+            Memo::new("element", "O").with(("number", 8)),
+            Memo::new("element", "O").with(("group", 16))
+        );
+        schema.iter_mut().for_each(|memo| memo.set_path("schema.mr"));
+        data.iter_mut().for_each(|memo| memo.set_path("data.mr"));
+        (schema, data)
+    }
+
+    #[test]
+    fn duplicates() {
+        assert_eq!("last-wins".parse(), Ok(Duplicates::LastWins));
+        assert!("first-wins".parse::<Duplicates>().is_err());
+
+        let (schema, data) = sources();
+        let mut db = Database::from(schema).with_index();
+        let msg = db.merge(data, Duplicates::Error).unwrap_err();
+        assert_eq!(msg, "@element Fe is defined in 'schema.mr' and 'data.mr'");
+        assert_eq!(db.len(), 2);
+
+        // duplicates within one file are kept
+        let (schema, data) = sources();
+        let mut db = Database::from(schema).with_index();
+        db.merge(data, Duplicates::LastWins).unwrap();
+        assert_eq!(db.len(), 4);
+        let iron = db.get_by_index(1).unwrap();
+        assert_eq!(iron.path(), Some(std::path::Path::new("data.mr")));
+        assert!(!iron.contains_key("metal"));
+        assert_eq!(db.collection("element").filter(|memo| memo.title() == "O").count(), 2);
+        assert_eq!(db.filter(&parse_mql("amu>50").unwrap()).count(), 1);
+
+        let (schema, data) = sources();
+        let mut db = Database::from(schema).with_index();
+        db.merge(data, Duplicates::Merge).unwrap();
+        assert_eq!(db.len(), 4);
+        let iron = db.get("element", "Fe").unwrap();
+        let keys: Vec<&str> = iron.data().map(|node| node.key.as_str()).collect();
+        assert_eq!(keys, vec!("number", "metal", "amu"));
+        assert_eq!(iron.path(), Some(std::path::Path::new("schema.mr")));
+        assert_eq!(db.filter(&parse_mql("amu>50,metal=yes").unwrap()).count(), 1);
+
+        // the indices are updated in place
+        let mut db = Database::from(vec!(
+            Memo::new("element", "Fe").with(("number", 26)).with(("metal", "yes")),
+            Memo::new("element", "H").with(("number", 1))
+        )).with_index();
+        let data = vec!(Memo::new("element", "Fe").with_attr("id", "fe").with(("number", 26)));
+        db.merge(data, Duplicates::LastWins).unwrap();
+        assert_eq!(db.index, Some(Index::new(&db.memos)));
+        assert_eq!(db.get_by_id("fe").unwrap().title(), "Fe");
+        assert!(db.get_by_id(&generate_id("element", "Fe")).is_none());
+        let data = vec!(Memo::new("element", "H").with(("metal", "no")).with(("amu", 1.008)));
+        db.merge(data, Duplicates::Merge).unwrap();
+        assert_eq!(db.index, Some(Index::new(&db.memos)));
+        assert_eq!(db.filter(&parse_mql("metal=no").unwrap()).count(), 1);
+        assert_eq!(db.filter(&parse_mql("metal=yes").unwrap()).count(), 0);
+    }
+
+    #[test]
+    fn read_from_files() {
+        let dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("data");
+        let periodic = dir.join("periodic.mr");
+        let apps = dir.join("apps.mr");
+        let files = [periodic.to_str().unwrap(), apps.to_str().unwrap()];
+
+        // both files define the template `basic`
+        assert!(Database::read_from_files(&files, Duplicates::Error).is_err());
+        let db = Database::read_from_files(&files, Duplicates::LastWins).unwrap();
+        let template = db.get("mr:template", "basic").unwrap();
+        assert_eq!(template.path(), Some(apps.as_path()));
+        assert!(db.get("app", "merula").is_some());
+        assert_eq!(db.get("element", "Fe").unwrap().path(), Some(periodic.as_path()));
+
+        // missing and unparsable files are errors
+        let msg = Database::read_from_files(&["/nonexistent.mr"], Duplicates::Error).unwrap_err();
+        assert!(msg.starts_with("cannot read '/nonexistent.mr': "), "{}", msg);
+        let msg = Database::read_from_path("/nonexistent.mr").unwrap_err();
+        assert!(msg.starts_with("cannot read '/nonexistent.mr': "), "{}", msg);
+        let temp = TempDir::new(&[("broken.mr", ".orphan\n")]);
+        let broken = temp.join("broken.mr");
+        let msg = Database::read_from_files(&[&broken], Duplicates::Error).unwrap_err();
+        assert_eq!(msg, format!("{}:1:1: cannot parse memo", broken));
+    }
+
+    #[test]
+    fn read_shared_include() {
+        let temp = TempDir::new(&[
+            ("a.mr", "@mr:include shared/common.mr\n\n@note a\n"),
+            ("b.mr", "@mr:include shared/common.mr\n\n@note b\n"),
+            ("shared/common.mr", "@note common\n"),
+            ("other/c.mr", "@mr:include ../shared/common.mr\n\n@note c\n")
+        ]);
+        let files = [temp.join("a.mr"), temp.join("b.mr"), temp.join("other")];
+        let files: Vec<&str> = files.iter().map(|file| file.as_str()).collect();
+        let db = Database::read_from_files(&files, Duplicates::Error).unwrap();
+        let titles: Vec<String> = db.iter().map(|memo| memo.title()).collect();
+        assert_eq!(titles, vec!("shared/common.mr", "a", "common", "shared/common.mr", "b",
+                                "../shared/common.mr", "c"));
+
+        // within a directory, the shared file is only read as part of a.mr and b.mr
+        let db = Database::read_from_files(&[temp.path().to_str().unwrap()], Duplicates::Error).unwrap();
+        assert_eq!(db.collection("note").count(), 4);
+    }
+
+    #[test]
+    fn read_from_dir() {
+        let temp = TempDir::new(&[
+            (".merulaignore", "drafts/\n"),
+            ("rust.mr", "@note borrowing\n.tag rust\n"),
+            ("drafts/ideas.mr", "@note ideas\n.tag rust\n"),
+            ("tools/cargo.mr", "@note cargo\n.tag rust\n"),
+            ("tools/shell.mr", "@note bash\n.tag shell\n")
+        ]);
+        let db = Database::read_from_files(&[temp.path().to_str().unwrap()], Duplicates::Error).unwrap();
+        assert_eq!(db.filter(&parse_mql("tag=rust").unwrap()).count(), 2);
+        let shell = temp.path().join("tools/shell.mr");
+        assert_eq!(db.get("note", "bash").unwrap().path(), Some(shell.as_path()));
+    }
+}
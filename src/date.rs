@@ -0,0 +1,364 @@
+//! Temporal values: Date, Time and DateTime.
+//!
+//! All three types are parsed from (a subset of) ISO-8601 and are
+//! printed back in exactly the same notation:
+//!
+//! - `Date`: `1892-01-03`, `1808-06` or `1808` (reduced precision)
+//! - `Time`: `14:30` or `14:30:15`
+//! - `DateTime`: `2021-01-24T14:30`, optionally followed by seconds
+//!   and a UTC offset, e.g. `2021-01-24T14:30:15Z` or
+//!   `2021-01-24T14:30+01:00`
+//!
+
+use std::str::FromStr;
+
+/// A calendar date.  Month and day may be omitted, which gives a
+/// date with reduced precision, i.e. a whole year or a whole month.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Date {
+    pub year: i32,
+    pub month: Option<u32>,
+    pub day: Option<u32>
+}
+
+/// A time of day with optional seconds.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Time {
+    pub hour: u32,
+    pub minute: u32,
+    pub second: Option<u32>
+}
+
+/// The offset of a DateTime from UTC.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Offset {
+    Utc,         // written as `Z`
+    Minutes(i32) // written as `+hh:mm` or `-hh:mm`
+}
+
+/// A complete date combined with a time of day.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct DateTime {
+    pub date: Date,
+    pub time: Time,
+    pub offset: Option<Offset>
+}
+
+pub type ParseResult<T> = Result<T, &'static str>;
+
+fn is_leap_year(year: i32) -> bool {
+    (year % 4 == 0 && year % 100 != 0) || year % 400 == 0
+}
+
+/// Returns the number of days of the given month.
+pub fn days_in_month(year: i32, month: u32) -> u32 {
+    match month {
+        1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+        4 | 6 | 9 | 11 => 30,
+        2 if is_leap_year(year) => 29,
+        _ => 28
+    }
+}
+
+// Parse a fixed-width, unsigned decimal number.
+fn parse_digits(s: &str, width: usize) -> ParseResult<u32> {
+    if s.len() == width && s.chars().all(|c| c.is_ascii_digit()) {
+        s.parse::<u32>().map_err(|_| "invalid number")
+    } else {
+        Err("unexpected number of digits")
+    }
+}
+
+impl Date {
+    /// Constructs a new, complete Date.  Returns None if the given
+    /// combination of year, month and day is not a valid date.
+    pub fn from_ymd(year: i32, month: u32, day: u32) -> Option<Date> {
+        if (1..=12).contains(&month) && day >= 1 && day <= days_in_month(year, month) {
+            Some(Date { year, month: Some(month), day: Some(day) })
+        } else {
+            None
+        }
+    }
+
+    /// Returns true if year, month and day are given.
+    pub fn is_complete(&self) -> bool {
+        self.day.is_some()
+    }
+
+    /// Returns the first day covered by the Date, e.g. `1808-01-01`
+    /// for the reduced date `1808`.
+    pub fn earliest(&self) -> Date {
+        let month = self.month.unwrap_or(1);
+        Date {
+            year: self.year,
+            month: Some(month),
+            day: Some(self.day.unwrap_or(1))
+        }
+    }
+
+    /// Returns the last day covered by the Date, e.g. `1808-06-30`
+    /// for the reduced date `1808-06`.
+    pub fn latest(&self) -> Date {
+        let month = self.month.unwrap_or(12);
+        Date {
+            year: self.year,
+            month: Some(month),
+            day: Some(self.day.unwrap_or_else(|| days_in_month(self.year, month)))
+        }
+    }
+
+    /// Returns true if the whole period covered by the Date lies
+    /// before the period covered by `other`.
+    pub fn is_before(&self, other: &Date) -> bool {
+        self.latest() < other.earliest()
+    }
+
+    /// Returns true if the whole period covered by the Date lies
+    /// after the period covered by `other`.
+    pub fn is_after(&self, other: &Date) -> bool {
+        self.earliest() > other.latest()
+    }
+
+    /// Returns true if the periods covered by both dates overlap,
+    /// e.g. `1808-06-30` is the same date as `1808-06` and `1808`.
+    pub fn is_same(&self, other: &Date) -> bool {
+        !self.is_before(other) && !self.is_after(other)
+    }
+}
+
+impl FromStr for Date {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> ParseResult<Date> {
+        let mut parts = s.split('-');
+        let year = parse_digits(parts.next().unwrap_or(""), 4)? as i32;
+        let month = match parts.next() {
+            Some(m) => Some(parse_digits(m, 2)?),
+            None => None
+        };
+        let day = match parts.next() {
+            Some(d) => Some(parse_digits(d, 2)?),
+            None => None
+        };
+        if parts.next().is_some() {
+            return Err("too many date components");
+        }
+        if let Some(month) = month {
+            if !(1..=12).contains(&month) {
+                return Err("month out of range");
+            }
+            if let Some(day) = day {
+                if day < 1 || day > days_in_month(year, month) {
+                    return Err("day out of range");
+                }
+            }
+        }
+        Ok(Date { year, month, day })
+    }
+}
+
+impl std::fmt::Display for Date {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:04}", self.year)?;
+        if let Some(month) = self.month {
+            write!(f, "-{:02}", month)?;
+        }
+        if let Some(day) = self.day {
+            write!(f, "-{:02}", day)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Time {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> ParseResult<Time> {
+        let mut parts = s.split(':');
+        let hour = parse_digits(parts.next().unwrap_or(""), 2)?;
+        let minute = parse_digits(parts.next().ok_or("missing minutes")?, 2)?;
+        let second = match parts.next() {
+            Some(sec) => Some(parse_digits(sec, 2)?),
+            None => None
+        };
+        if parts.next().is_some() {
+            return Err("too many time components");
+        }
+        // allow a leap second, but nothing beyond
+        if hour > 23 || minute > 59 || second.unwrap_or(0) > 60 {
+            return Err("time out of range");
+        }
+        Ok(Time { hour, minute, second })
+    }
+}
+
+impl std::fmt::Display for Time {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:02}:{:02}", self.hour, self.minute)?;
+        if let Some(second) = self.second {
+            write!(f, ":{:02}", second)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Offset {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> ParseResult<Offset> {
+        if s == "Z" {
+            return Ok(Offset::Utc);
+        }
+        let sign = match s.chars().next() {
+            Some('+') => 1,
+            Some('-') => -1,
+            _ => return Err("offset must start with '+', '-' or be 'Z'")
+        };
+        let time = Time::from_str(&s[1..])?;
+        if time.second.is_some() {
+            return Err("offset must not contain seconds");
+        }
+        Ok(Offset::Minutes(sign * (time.hour * 60 + time.minute) as i32))
+    }
+}
+
+impl std::fmt::Display for Offset {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Offset::Utc => write!(f, "Z"),
+            Offset::Minutes(minutes) => {
+                let sign = if *minutes < 0 { '-' } else { '+' };
+                let minutes = minutes.abs();
+                write!(f, "{}{:02}:{:02}", sign, minutes / 60, minutes % 60)
+            }
+        }
+    }
+}
+
+impl FromStr for DateTime {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> ParseResult<DateTime> {
+        let (date, rest) = match s.find('T') {
+            Some(pos) => (&s[..pos], &s[pos + 1..]),
+            None => return Err("missing 'T' between date and time")
+        };
+
+        let date = Date::from_str(date)?;
+        if !date.is_complete() {
+            return Err("date part of a DateTime must be complete");
+        }
+
+        let (time, offset) = match rest.find(['Z', '+', '-']) {
+            Some(pos) => (&rest[..pos], Some(Offset::from_str(&rest[pos..])?)),
+            None => (rest, None)
+        };
+        let time = Time::from_str(time)?;
+
+        Ok(DateTime { date, time, offset })
+    }
+}
+
+impl std::fmt::Display for DateTime {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}T{}", self.date, self.time)?;
+        if let Some(offset) = self.offset {
+            write!(f, "{}", offset)?;
+        }
+        Ok(())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_date() {
+        assert_eq!("1892-01-03".parse::<Date>(), Ok(Date::from_ymd(1892, 1, 3).unwrap()));
+        assert_eq!("1808-06".parse::<Date>(),
+                   Ok(Date { year: 1808, month: Some(6), day: None }));
+        assert_eq!("1808".parse::<Date>(),
+                   Ok(Date { year: 1808, month: None, day: None }));
+
+        assert!("2021-02-29".parse::<Date>().is_err());
+        assert!("2020-02-29".parse::<Date>().is_ok());
+        assert!("2021-13-01".parse::<Date>().is_err());
+        assert!("2021-1-1".parse::<Date>().is_err());
+        assert!("21-01-01".parse::<Date>().is_err());
+        assert!("2021-01-01-01".parse::<Date>().is_err());
+        assert!("foo".parse::<Date>().is_err());
+    }
+
+    #[test]
+    fn parse_time() {
+        assert_eq!("14:30".parse::<Time>(),
+                   Ok(Time { hour: 14, minute: 30, second: None }));
+        assert_eq!("14:30:15".parse::<Time>(),
+                   Ok(Time { hour: 14, minute: 30, second: Some(15) }));
+        assert!("24:00".parse::<Time>().is_err());
+        assert!("14".parse::<Time>().is_err());
+        assert!("14:3".parse::<Time>().is_err());
+    }
+
+    #[test]
+    fn parse_date_time() {
+        let dt = "2021-01-24T14:30".parse::<DateTime>().unwrap();
+        assert_eq!(dt.date, Date::from_ymd(2021, 1, 24).unwrap());
+        assert_eq!(dt.time, Time { hour: 14, minute: 30, second: None });
+        assert_eq!(dt.offset, None);
+
+        let dt = "2021-01-24T14:30:15Z".parse::<DateTime>().unwrap();
+        assert_eq!(dt.offset, Some(Offset::Utc));
+
+        let dt = "2021-01-24T14:30-02:30".parse::<DateTime>().unwrap();
+        assert_eq!(dt.offset, Some(Offset::Minutes(-150)));
+
+        assert!("2021-01T14:30".parse::<DateTime>().is_err());
+        assert!("2021-01-24 14:30".parse::<DateTime>().is_err());
+    }
+
+    #[test]
+    fn display_roundtrip() {
+        for s in &["1892-01-03", "1808-06", "1808", "0042-01-01"] {
+            assert_eq!(s.parse::<Date>().unwrap().to_string(), *s);
+        }
+        for s in &["00:00", "14:30:15"] {
+            assert_eq!(s.parse::<Time>().unwrap().to_string(), *s);
+        }
+        for s in &["2021-01-24T14:30", "2021-01-24T14:30:15Z", "2021-01-24T14:30+01:00"] {
+            assert_eq!(s.parse::<DateTime>().unwrap().to_string(), *s);
+        }
+    }
+
+    #[test]
+    fn reduced_precision() {
+        let date = |s: &str| s.parse::<Date>().unwrap();
+        assert_eq!(date("1808").earliest(), date("1808-01-01"));
+        assert_eq!(date("1808").latest(), date("1808-12-31"));
+        assert_eq!(date("2020-02").latest(), date("2020-02-29"));
+        assert_eq!(date("1808-06-30").latest(), date("1808-06-30"));
+
+        assert!(date("1808-06-30").is_same(&date("1808")));
+        assert!(date("1808-06-30").is_same(&date("1808-06")));
+        assert!(!date("1808-06-30").is_same(&date("1808-07")));
+        assert!(date("1808-06-30").is_before(&date("1808-07")));
+        assert!(!date("1808-06-30").is_before(&date("1808")));
+        assert!(date("1809").is_after(&date("1808-12-31")));
+        assert!(!date("1808-12").is_after(&date("1808")));
+    }
+
+    #[test]
+    fn chronological_order() {
+        let d1 = "1892-01-03".parse::<Date>().unwrap();
+        let d2 = "1952-03-11".parse::<Date>().unwrap();
+        let d3 = "1952-11-01".parse::<Date>().unwrap();
+        assert!(d1 < d2);
+        assert!(d2 < d3);
+
+        let t1 = "09:15".parse::<Time>().unwrap();
+        let t2 = "14:30".parse::<Time>().unwrap();
+        assert!(t1 < t2);
+    }
+}
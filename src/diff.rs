@@ -0,0 +1,228 @@
+//! Structural diff between two lists of memos.
+//!
+//! Memos are matched by collection and title.  A memo that only
+//! exists in the old list has been removed, a memo that only exists
+//! in the new list has been added.  For memos that exist in both
+//! lists, the data nodes are compared key by key:
+//!
+//! ```text
+//! @element Fe              @element Fe
+//! .amu 55.8           =>   .amu 55.845
+//! .discovery Cavendish     .discovery Cavendish
+//!                          +date 1808-06-30
+//! ```
+//!
+//! results in a changed `.amu` node and a changed `.discovery` node
+//! with an added attribute `+date`.
+//!
+//! Nodes with the same key are matched in order, after equal nodes
+//! have been paired up.  A mere change in the order of nodes is
+//! therefore not reported.  Comments and locations are ignored.
+//!
+
+use std::collections::HashMap;
+
+use crate::{Memo, Node, Key, Value};
+
+/// A change of a single attribute.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AttrChange<'a> {
+    Added(&'a Key, &'a Value),
+    Removed(&'a Key, &'a Value)
+}
+
+/// A change of a single node within a memo.
+#[derive(Debug, Clone, PartialEq)]
+pub enum NodeChange<'a> {
+    Added(&'a Node),
+    Removed(&'a Node),
+    // value and/or attributes have changed
+    Changed { old: &'a Node, new: &'a Node, attrs: Vec<AttrChange<'a>> }
+}
+
+/// A change of a memo within a list of memos.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Change<'a> {
+    Added(&'a Memo),
+    Removed(&'a Memo),
+    // the header node is part of `nodes`, if its attributes changed
+    Changed { old: &'a Memo, new: &'a Memo, nodes: Vec<NodeChange<'a>> }
+}
+
+// Pairs up the items of `old` and `new`.  Equal items are matched
+// first, the remaining items are matched in order.  Returns the
+// pairs of unequal items and the items without counterpart.
+fn match_items<'a, T: PartialEq>(old: &[&'a T], new: &[&'a T])
+                                 -> (Vec<(&'a T, &'a T)>, Vec<&'a T>, Vec<&'a T>)
+{
+    let mut unmatched: Vec<Option<&'a T>> = new.iter().map(|&item| Some(item)).collect();
+    let mut rest = Vec::new();
+    for &item in old {
+        match unmatched.iter_mut().find(|other| *other == &Some(item)) {
+            Some(other) => *other = None,
+            None => rest.push(item)
+        }
+    }
+    let mut added: Vec<&'a T> = unmatched.into_iter().flatten().collect();
+    let count = rest.len().min(added.len());
+    let removed = rest.split_off(count);
+    let pairs = rest.into_iter().zip(added.drain(..count)).collect();
+    (pairs, removed, added)
+}
+
+/// Returns the attributes that were added or removed between `old`
+/// and `new`.  A changed attribute value is reported as removal of
+/// the old value and addition of the new one.
+pub fn diff_attrs<'a>(old: &'a Node, new: &'a Node) -> Vec<AttrChange<'a>> {
+    let old_attrs: Vec<&(Key, Value)> = old.attrs().iter().collect();
+    let new_attrs: Vec<&(Key, Value)> = new.attrs().iter().collect();
+    let (pairs, removed, added) = match_items(&old_attrs, &new_attrs);
+    let mut changes = Vec::new();
+    for (old, new) in pairs {
+        changes.push(AttrChange::Removed(&old.0, &old.1));
+        changes.push(AttrChange::Added(&new.0, &new.1));
+    }
+    changes.extend(removed.into_iter().map(|(key, value)| AttrChange::Removed(key, value)));
+    changes.extend(added.into_iter().map(|(key, value)| AttrChange::Added(key, value)));
+    changes
+}
+
+fn changed_node<'a>(old: &'a Node, new: &'a Node) -> NodeChange<'a> {
+    NodeChange::Changed { old, new, attrs: diff_attrs(old, new) }
+}
+
+/// Returns the changes between two versions of a memo.  Collection
+/// and title are not compared, but the attributes of the header are.
+pub fn diff_memo<'a>(old: &'a Memo, new: &'a Memo) -> Vec<NodeChange<'a>> {
+    let mut changes = Vec::new();
+    if old.header().attrs() != new.header().attrs() {
+        changes.push(changed_node(old.header(), new.header()));
+    }
+
+    // keys in order of their first appearance
+    let mut keys: Vec<&Key> = Vec::new();
+    for node in old.data().chain(new.data()) {
+        if !keys.contains(&&node.key) {
+            keys.push(&node.key);
+        }
+    }
+
+    for key in keys {
+        let old_nodes: Vec<&Node> = old.data().filter(|n| &n.key == key).collect();
+        let new_nodes: Vec<&Node> = new.data().filter(|n| &n.key == key).collect();
+        let (pairs, removed, added) = match_items(&old_nodes, &new_nodes);
+        changes.extend(pairs.into_iter().map(|(old, new)| changed_node(old, new)));
+        changes.extend(removed.into_iter().map(NodeChange::Removed));
+        changes.extend(added.into_iter().map(NodeChange::Added));
+    }
+    changes
+}
+
+/// Returns the changes between two lists of memos.  Changed and
+/// removed memos are listed in the order of `old`, followed by the
+/// added memos in the order of `new`.  If several memos share the
+/// same collection and title, they are matched in order.
+pub fn diff<'a>(old: &'a [Memo], new: &'a [Memo]) -> Vec<Change<'a>> {
+    // indices of the new memos for each collection and title,
+    // reversed so that the first one can be popped
+    let mut candidates: HashMap<(Key, String), Vec<usize>> = HashMap::new();
+    for (index, memo) in new.iter().enumerate().rev() {
+        candidates.entry((memo.collection(), memo.title()))
+            .or_default()
+            .push(index);
+    }
+
+    let mut changes = Vec::new();
+    let mut matched = vec![false; new.len()];
+    for memo in old {
+        match candidates.get_mut(&(memo.collection(), memo.title())).and_then(|c| c.pop()) {
+            Some(index) => {
+                matched[index] = true;
+                let nodes = diff_memo(memo, &new[index]);
+                if !nodes.is_empty() {
+                    changes.push(Change::Changed { old: memo, new: &new[index], nodes });
+                }
+            },
+            None => changes.push(Change::Removed(memo))
+        }
+    }
+    changes.extend(new.iter().zip(matched)
+                   .filter(|(_, matched)| !matched)
+                   .map(|(memo, _)| Change::Added(memo)));
+    changes
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn diff_nodes() {
+        let old = Memo::new("element", "Fe")
+            .with(("amu", 55.8))
+            .with(("discovery", "Cavendish"))
+            .with(("color", "grey"))
+            .with(("color", "silver"));
+        let new = Memo::new("element", "Fe")
+            .with_attr("id", "fe")
+            .with(("amu", 55.845))
+            .with(("discovery", "Cavendish"))
+            .with_attr("date", Value::infer("1808-06-30"))
+            .with(("color", "silver"))
+            .with(("symbol", "Fe"));
+
+        let changes = diff_memo(&old, &new);
+        assert_eq!(changes.len(), 5);
+        assert_eq!(changes[0], NodeChange::Changed {
+            old: old.header(), new: new.header(),
+            attrs: vec!(AttrChange::Added(&"id".into(), &Value::from("fe")))
+        });
+        assert!(matches!(changes[1], NodeChange::Changed { old, new, ref attrs }
+                         if old.key == "amu" && new.value == Value::from(55.845) && attrs.is_empty()));
+        assert!(matches!(changes[2], NodeChange::Changed { ref attrs, .. }
+                         if attrs.len() == 1));
+        assert!(matches!(changes[3], NodeChange::Removed(node) if node.value == Value::from("grey")));
+        assert!(matches!(changes[4], NodeChange::Added(node) if node.key == "symbol"));
+
+        assert!(diff_memo(&old, &old).is_empty());
+
+        // a rewritten number is a change, even if its value is the same
+        let old = Memo::new("element", "Te").with(("amu", Value::infer("127.60")));
+        let new = Memo::new("element", "Te").with(("amu", Value::infer("127.6")));
+        assert_eq!(diff_memo(&old, &new).len(), 1);
+    }
+
+    #[test]
+    fn diff_attr_values() {
+        let old = Node::new("density", 7.87).with_attr("unit", "g/cm³").with_attr("source", "x");
+        let new = Node::new("density", 7.87).with_attr("source", "x").with_attr("unit", "kg/m³");
+        let unit = Key::from("unit");
+        assert_eq!(diff_attrs(&old, &new),
+                   vec!(AttrChange::Removed(&unit, &Value::from("g/cm³")),
+                        AttrChange::Added(&unit, &Value::from("kg/m³"))));
+    }
+
+    #[test]
+    fn diff_memos() {
+        let old = vec!(
+            Memo::new("element", "H").with(("number", 1)),
+            Memo::new("element", "He").with(("number", 2)),
+            Memo::new("element", "Li").with(("number", 3))
+        );
+        let new = vec!(
+            Memo::new("element", "Li").with(("number", 3)),
+            Memo::new("element", "H").with(("number", 1)).with(("symbol", "H")),
+            Memo::new("element", "Be").with(("number", 4))
+        );
+        let changes = diff(&old, &new);
+        assert_eq!(changes.len(), 3);
+        assert!(matches!(changes[0], Change::Changed { old, ref nodes, .. }
+                         if old.title() == "H" && nodes.len() == 1));
+        assert!(matches!(changes[1], Change::Removed(memo) if memo.title() == "He"));
+        assert!(matches!(changes[2], Change::Added(memo) if memo.title() == "Be"));
+
+        assert!(diff(&old, &old).is_empty());
+        assert_eq!(diff(&[], &old).len(), 3);
+    }
+}
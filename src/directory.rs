@@ -0,0 +1,271 @@
+//! Databases kept as a directory tree of `.mr` files.
+//!
+//! Instead of a single file, a database may consist of one `.mr` file
+//! per topic in a directory tree:
+//!
+//! ```text
+//! notes/
+//! ├── .merulaignore
+//! ├── rust.mr
+//! ├── drafts/
+//! │   └── ideas.mr
+//! └── tools/
+//!     └── shell.mr
+//! ```
+//!
+//! `find_files` returns all `.mr` files below a directory, and
+//! `parser::read_from_path` reads them as if they were one file.
+//!
+//! Each directory may contain a file `.merulaignore` with patterns of
+//! files and directories to skip, one per line, similar to
+//! `.gitignore`.  The patterns apply to the directory of the
+//! `.merulaignore` file and all directories below it.
+//!
+//! - Empty lines and lines starting with `#` are skipped.
+//! - `*` matches any characters except `/`, `**` matches any
+//!   characters and `?` matches a single character.
+//! - A pattern ending with `/` only matches directories.
+//! - A pattern containing a `/` elsewhere matches the path relative
+//!   to the directory of the `.merulaignore` file, e.g. `/drafts` or
+//!   `tools/old.mr`.  Any other pattern matches the name of a file or
+//!   directory at any depth, e.g. `*.bak.mr`.
+//!
+//! Negated patterns (`!`) are not supported.  Hidden files and
+//! directories, i.e. those starting with a `.`, are always skipped,
+//! and symbolic links are not followed.
+//!
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use log::*;
+use regex::Regex;
+
+/// Name of the file with patterns of files to skip.
+pub const IGNORE_FILE: &str = ".merulaignore";
+
+/// Extension of the files that are read.
+pub const EXTENSION: &str = "mr";
+
+// A pattern of an ignore file, see the module documentation.
+#[derive(Debug)]
+struct Pattern {
+    base: PathBuf, // directory of the ignore file
+    regex: Regex,
+    dir_only: bool,
+    anchored: bool // match the relative path instead of the name
+}
+
+impl Pattern {
+    // Parses a line of the ignore file in the directory `base`.
+    fn new(base: &Path, line: &str) -> Option<Pattern> {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            return None;
+        }
+        let dir_only = line.ends_with('/');
+        let glob = line.trim_end_matches('/');
+        let anchored = glob.contains('/');
+        let glob = glob.trim_start_matches('/');
+
+        let mut re = String::from("^");
+        let mut chars = glob.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '*' if chars.peek() == Some(&'*') => {
+                    chars.next();
+                    re.push_str(".*");
+                },
+                '*' => re.push_str("[^/]*"),
+                '?' => re.push_str("[^/]"),
+                c => re.push_str(&regex::escape(&c.to_string()))
+            }
+        }
+        re.push('$');
+
+        Some(Pattern {
+            base: base.to_path_buf(),
+            regex: Regex::new(&re).ok()?,
+            dir_only,
+            anchored
+        })
+    }
+
+    // Returns true if the pattern matches the given path.
+    fn matches(&self, path: &Path, is_dir: bool) -> bool {
+        if self.dir_only && !is_dir {
+            return false;
+        }
+        let relative = match path.strip_prefix(&self.base) {
+            Ok(relative) => relative,
+            Err(_) => return false
+        };
+        let text = match self.anchored {
+            true => relative.components()
+                .map(|c| c.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/"),
+            false => relative.file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_default()
+        };
+        self.regex.is_match(&text)
+    }
+}
+
+// Adds the `.mr` files below `dir` to `files`.  The `patterns` are
+// those of the ignore files of `dir` and its parent directories.
+fn visit(dir: &Path, patterns: &mut Vec<Pattern>, files: &mut Vec<PathBuf>) -> io::Result<()> {
+    let count = patterns.len();
+    if let Ok(text) = fs::read_to_string(dir.join(IGNORE_FILE)) {
+        patterns.extend(text.lines().filter_map(|line| Pattern::new(dir, line)));
+    }
+
+    let mut entries = fs::read_dir(dir)?.collect::<io::Result<Vec<fs::DirEntry>>>()?;
+    entries.sort_by_key(|entry| entry.file_name());
+    for entry in entries {
+        let path = entry.path();
+        let file_type = entry.file_type()?;
+        let hidden = entry.file_name().to_string_lossy().starts_with('.');
+        if hidden || patterns.iter().any(|p| p.matches(&path, file_type.is_dir())) {
+            debug!("skipping '{}'", path.display());
+            continue;
+        }
+        if file_type.is_dir() {
+            visit(&path, patterns, files)?;
+        } else if file_type.is_file() && path.extension() == Some(EXTENSION.as_ref()) {
+            files.push(path);
+        }
+    }
+
+    patterns.truncate(count);
+    Ok(())
+}
+
+/// Returns the paths of all `.mr` files below the given directory,
+/// sorted by path, except for those matching a `.merulaignore`.
+pub fn find_files<P: AsRef<Path>>(dir: P) -> io::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    visit(dir.as_ref(), &mut Vec::new(), &mut files)?;
+    Ok(files)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{read_from_path, read_memos_from_path};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    // A directory tree with the given files and contents in the
+    // temporary directory, which is removed when dropped.  Each
+    // instance gets its own directory, so tests can run in parallel.
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(files: &[(&str, &str)]) -> TempDir {
+            static COUNT: AtomicUsize = AtomicUsize::new(0);
+            let dir = std::env::temp_dir().join(format!(
+                "merula-directory-{}-{}", std::process::id(), COUNT.fetch_add(1, Ordering::SeqCst)));
+            let _ = fs::remove_dir_all(&dir);
+            for (file, content) in files {
+                let path = dir.join(file);
+                fs::create_dir_all(path.parent().unwrap()).unwrap();
+                fs::write(path, content).unwrap();
+            }
+            TempDir(dir)
+        }
+
+        fn path(&self) -> &Path {
+            &self.0
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn sample_dir() -> TempDir {
+        TempDir::new(&[
+            (".merulaignore", "# skipped\ndrafts/\n*.bak.mr\n/tools/old.mr\n"),
+            ("rust.mr", "@mr:include shared/common.mr\n\n@note borrowing\n.tag rust\n"),
+            ("shared/common.mr", "@note lifetimes\n.tag rust\n"),
+            ("drafts/ideas.mr", "@note ideas\n.tag rust\n"),
+            ("tools/shell.mr", "@note bash\n.tag shell\n"),
+            ("tools/old.mr", "@note sh\n"),
+            ("tools/shell.bak.mr", "@note bash\n"),
+            ("tools/.hidden.mr", "@note hidden\n"),
+            ("tools/README.txt", "not a memo"),
+            ("tools/sub/.merulaignore", "zsh.mr"),
+            ("tools/sub/zsh.mr", "@note zsh\n"),
+            ("tools/sub/fish.mr", "@note fish\n")
+        ])
+    }
+
+    #[test]
+    fn ignore_patterns() {
+        let base = Path::new("notes");
+        let pattern = Pattern::new(base, "*.bak.mr").unwrap();
+        assert!(pattern.matches(Path::new("notes/a/b.bak.mr"), false));
+        assert!(!pattern.matches(Path::new("notes/a/b.mr"), false));
+        assert!(!pattern.matches(Path::new("other/b.bak.mr"), false));
+
+        let pattern = Pattern::new(base, "/a/?.mr").unwrap();
+        assert!(pattern.matches(Path::new("notes/a/b.mr"), false));
+        assert!(!pattern.matches(Path::new("notes/x/a/b.mr"), false));
+
+        let pattern = Pattern::new(base, "a/**.mr").unwrap();
+        assert!(pattern.matches(Path::new("notes/a/b/c.mr"), false));
+
+        let pattern = Pattern::new(base, "drafts/").unwrap();
+        assert!(pattern.matches(Path::new("notes/x/drafts"), true));
+        assert!(!pattern.matches(Path::new("notes/x/drafts"), false));
+
+        assert!(Pattern::new(base, "# comment").is_none());
+        assert!(Pattern::new(base, "  ").is_none());
+    }
+
+    #[test]
+    fn find_and_read_files() {
+        let temp = sample_dir();
+        let dir = temp.path();
+        let files: Vec<PathBuf> = find_files(dir).unwrap().iter()
+            .map(|path| path.strip_prefix(dir).unwrap().to_path_buf())
+            .collect();
+        let expected: Vec<PathBuf> = ["rust.mr", "shared/common.mr", "tools/shell.mr", "tools/sub/fish.mr"]
+            .iter().map(PathBuf::from).collect();
+        assert_eq!(files, expected);
+
+        // shared/common.mr is only read as part of rust.mr
+        let memos = read_from_path(dir.to_str().unwrap()).unwrap();
+        let titles: Vec<String> = memos.iter().map(|memo| memo.title()).collect();
+        assert_eq!(titles, vec!("shared/common.mr", "borrowing", "lifetimes", "bash", "fish"));
+        assert_eq!(memos[2].path(), Some(dir.join("shared/common.mr").as_path()));
+        assert_eq!(memos[3].path(), Some(dir.join("tools/shell.mr").as_path()));
+
+        assert!(find_files(dir.join("missing")).is_err());
+    }
+
+    #[test]
+    fn read_errors() {
+        // an unparsable file is reported with its position
+        let temp = TempDir::new(&[("a.mr", "@note a\n"), ("b.mr", ".orphan\n")]);
+        let dir = temp.path();
+        assert!(read_from_path(dir.to_str().unwrap()).is_err());
+        let msg = read_memos_from_path(dir.to_str().unwrap()).unwrap_err();
+        assert_eq!(msg, format!("{}:1:1: cannot parse memo", dir.join("b.mr").display()));
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::ffi::OsStrExt;
+            let temp = TempDir::new(&[("a.mr", "@note a\n")]);
+            let dir = temp.path();
+            fs::write(dir.join(std::ffi::OsStr::from_bytes(b"b\xff.mr")), "@note b\n").unwrap();
+            let msg = read_memos_from_path(dir.to_str().unwrap()).unwrap_err();
+            assert!(msg.ends_with("b\u{fffd}.mr': path is not valid UTF-8"), "{}", msg);
+        }
+    }
+}
@@ -1,5 +1,7 @@
 
-use crate::Memo;
+use crate::{Memo, Node};
+use crate::location::Location;
+use crate::diff::{Change, NodeChange, AttrChange};
 
 use colored::*;
 
@@ -12,18 +14,101 @@ pub fn print_header(memo: &Memo) {
     );
 }
 
+pub fn print_location(location: Option<Location>) {
+    if let Some(location) = location {
+        println!("  {}", location.to_string().dimmed());
+    }
+}
+
+// Marks nodes and attributes inherited from a prototype.
+fn inherited_mark(inherited: bool) -> ColoredString {
+    match inherited {
+        true => " (inherited)".dimmed(),
+        false => "".normal()
+    }
+}
+
 pub fn print_data_nodes(memo: &Memo) {
     for node in memo.data() {
-        println!("{}{} {}",
-                 ".".red(),
-                 node.key.red(),
-                 node.value.to_string().white());
-        
-        for (key, value) in node.attrs() {
-            println!("{}{} {}",
-                     "+".yellow(),
-                     key.yellow(),
-                     value.to_string().white());
+        print_data_node(node, None);
+    }
+}
+
+/// Prints a data node with its attributes, followed by the given
+/// location of the node, if any.
+pub fn print_data_node(node: &Node, location: Option<Location>) {
+    println!("{}{} {}{}",
+             ".".red(),
+             node.key.red(),
+             node.value.to_string().white(),
+             inherited_mark(node.is_inherited()));
+    print_location(location);
+
+    for (key, value) in node.attrs() {
+        println!("{}{} {}{}",
+                 "+".yellow(),
+                 key.yellow(),
+                 value.to_string().white(),
+                 inherited_mark(!node.is_inherited() && node.is_inherited_attr(key.as_str())));
+    }
+}
+
+// Returns a node as `.key value`, or as `@collection title` for the
+// header node of the given memo.
+fn node_line(memo: &Memo, node: &Node) -> String {
+    let prefix = if std::ptr::eq(node, memo.header()) { "@" } else { "." };
+    format!("{}{} {}", prefix, node.key, node.value)
+}
+
+// Prints a changed node of the given memo, indented by two spaces.
+fn print_node_change(memo: &Memo, change: &NodeChange) {
+    match change {
+        NodeChange::Added(node) => {
+            println!("{}", format!("  + {}", node_line(memo, node)).green());
+        },
+        NodeChange::Removed(node) => {
+            println!("{}", format!("  - {}", node_line(memo, node)).red());
+        },
+        NodeChange::Changed { old, new, attrs } => {
+            if old.value == new.value {
+                println!("    {}", node_line(memo, new));
+            } else {
+                println!("{}", format!("  - {}", node_line(memo, old)).red());
+                println!("{}", format!("  + {}", node_line(memo, new)).green());
+            }
+            for attr in attrs {
+                match attr {
+                    AttrChange::Added(key, value) =>
+                        println!("{}", format!("  +   +{} {}", key, value).green()),
+                    AttrChange::Removed(key, value) =>
+                        println!("{}", format!("  -   +{} {}", key, value).red())
+                }
+            }
+        }
+    }
+}
+
+/// Prints the given changes in a diff-like format, i.e. added memos
+/// and nodes prefixed with `+`, removed ones with `-`.
+pub fn print_diff(changes: &[Change]) {
+    for change in changes {
+        match change {
+            Change::Added(memo) => {
+                println!("{}", format!("+ @{} {}", memo.collection(), memo.title()).green().bold());
+            },
+            Change::Removed(memo) => {
+                println!("{}", format!("- @{} {}", memo.collection(), memo.title()).red().bold());
+            },
+            Change::Changed { new, nodes, .. } => {
+                println!("{} {}{} {}",
+                         "~".yellow().bold(),
+                         "@".yellow().bold(),
+                         new.collection().yellow().bold(),
+                         new.title().white().bold());
+                for node in nodes {
+                    print_node_change(new, node);
+                }
+            }
         }
     }
 }
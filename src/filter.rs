@@ -5,6 +5,9 @@
 
 use crate::{Memo, Node, Key, Value};
 use crate::memo::NodeType;
+use crate::date::Date;
+use crate::unit::Unit;
+use crate::index::Index;
 
 use std::convert::TryFrom;
 use std::collections::HashSet;
@@ -52,59 +55,167 @@ pub enum ValueFilter {
     Any,
     Equals(String),
     Contains(String),
-    LessThan(f32),
-    MoreThan(f32),
-    AtLeast(f32),
-    AtMost(f32)
+    LessThan(f64),
+    MoreThan(f64),
+    AtLeast(f64),
+    AtMost(f64),
+    Before(Date),
+    After(Date),
+    SameDate(Date),
+    InUnit(Box<ValueFilter>, Unit), // convert quantity to unit, then check
+    Within(Box<ValueFilter>) // all values of a range must match
 }
 
 
 impl ValueFilter {
+    /// Returns true if the ValueFilter compares numbers.
+    ///
+    /// A numeric filter matches a Value::Range if at least one value
+    /// of the range matches (overlap), e.g. `1.8 - 2.1` matches both
+    /// `>2` and `<2`.  Wrap the filter in `ValueFilter::Within` to
+    /// require that all values of the range match.
+    pub fn is_numeric(&self) -> bool {
+        matches!(self,
+                 ValueFilter::LessThan(_) | ValueFilter::MoreThan(_) |
+                 ValueFilter::AtLeast(_) | ValueFilter::AtMost(_) |
+                 ValueFilter::InUnit(_, _))
+    }
+
     pub fn check(&self, value: &Value) -> bool {
+        // comparisons are monotonic, so it is sufficient to check
+        // the bounds of a range
+        if let Some((from, to)) = value.bounds() {
+            match self {
+                ValueFilter::Within(inner) => return inner.check(&from) && inner.check(&to),
+                _ if self.is_numeric() => return self.check(&from) || self.check(&to),
+                _ => {}
+            }
+        }
+
         match self {
             ValueFilter::Any => true,
-            ValueFilter::Equals(x) => &value.to_string() == x,
+            ValueFilter::Equals(x) => {
+                match &value {
+                    // a reference matches its title as well
+                    Value::Ref(_, title) => title == x || &value.to_string() == x,
+                    Value::RefById(id) => id == x || &value.to_string() == x,
+                    // `yes` and `no` are accepted for booleans
+                    Value::Bool(b) => Value::parse_bool(x) == Some(*b),
+                    // numbers are compared numerically, so that
+                    // `amu=127.6` matches `127.60`
+                    Value::Integer(_) | Value::Float(_, _) =>
+                        &value.to_string() == x
+                        || x.parse::<f64>().ok() == f64::try_from(value).ok(),
+                    _ => &value.to_string() == x
+                }
+            },
             ValueFilter::Contains(x) => {
                 match &value {
                     Value::Text(value_text) => value_text.contains (x),
+                    Value::Ref(_, title) => title.contains(x),
+                    Value::RefById(id) => id.contains(x),
                     Value::MultiLineText(value_text, _) => value_text.contains(x),
-                    _ => false
+                    Value::Bool(_) => false,
+                    _ => value.to_string().contains(x)
                 }
             },
             ValueFilter::LessThan(x) => {
-                match f32::try_from(value) {
-                    Ok(value_f32) => value_f32 < *x,
+                match f64::try_from(value) {
+                    Ok(value_f64) => value_f64 < *x,
                     Err(_) => false
                 }
             },
             ValueFilter::MoreThan(x) => {
-                match f32::try_from(value) {
-                    Ok(value_f32) => value_f32 > *x,
+                match f64::try_from(value) {
+                    Ok(value_f64) => value_f64 > *x,
                     Err(_) => false
                 }                
             },
             ValueFilter::AtMost(x) => {
-                match f32::try_from(value) {
-                    Ok(value_f32) => value_f32 <= *x,
+                match f64::try_from(value) {
+                    Ok(value_f64) => value_f64 <= *x,
                     Err(_) => false
                 }
             },
             ValueFilter::AtLeast(x) => {
-                match f32::try_from(value) {
-                    Ok(value_f32) => value_f32 >= *x,
+                match f64::try_from(value) {
+                    Ok(value_f64) => value_f64 >= *x,
                     Err(_) => false
                 }                
-            }
+            },
+            ValueFilter::Before(x) => {
+                match Date::try_from(value) {
+                    Ok(date) => date.is_before(x),
+                    Err(_) => false
+                }
+            },
+            ValueFilter::After(x) => {
+                match Date::try_from(value) {
+                    Ok(date) => date.is_after(x),
+                    Err(_) => false
+                }
+            },
+            ValueFilter::SameDate(x) => {
+                match Date::try_from(value) {
+                    Ok(date) => date.is_same(x),
+                    Err(_) => false
+                }
+            },
+            ValueFilter::InUnit(inner, unit) => {
+                match value.quantity() {
+                    Some((x, value_unit)) => inner.check_quantity(x, &value_unit, unit),
+                    None => false
+                }
+            },
+            ValueFilter::Within(inner) => inner.check(value)
 
         }
     }
 }
 
+impl ValueFilter {
+    /// Checks the value of the given node.  Unlike `check`, this
+    /// takes the attribute `+unit` into account (see
+    /// `Node::quantity`).
+    pub fn check_node(&self, node: &Node) -> bool {
+        match (self, node.value.bounds()) {
+            (ValueFilter::Within(inner), Some((from, to))) =>
+                inner.check_bound(node, &from) && inner.check_bound(node, &to),
+            (ValueFilter::Within(inner), _) => inner.check_node(node),
+            (_, Some((from, to))) if self.is_numeric() =>
+                self.check_bound(node, &from) || self.check_bound(node, &to),
+            _ => self.check_bound(node, &node.value)
+        }
+    }
+
+    // Check the node value or one of the bounds of a range value.
+    fn check_bound(&self, node: &Node, value: &Value) -> bool {
+        match self {
+            ValueFilter::InUnit(inner, unit) => {
+                match node.quantity_of(value) {
+                    Some((x, node_unit)) => inner.check_quantity(x, &node_unit, unit),
+                    None => false
+                }
+            },
+            _ => self.check(value)
+        }
+    }
+
+    // Convert `x` from unit `from` into unit `to` and check it.
+    fn check_quantity(&self, x: f64, from: &Unit, to: &Unit) -> bool {
+        match from.convert(x, to) {
+            Some(x) => self.check(&Value::from(x)),
+            None => false
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct NodeFilter {
     pub node_type: NodeType,
     pub key: KeyFilter,
     pub index: IndexFilter,
+    pub attr: Option<Key>, // check attribute values instead of node value
     pub value: ValueFilter
 }
 
@@ -114,6 +225,7 @@ impl Default for NodeFilter {
             node_type: NodeType::Any,
             key: KeyFilter::Any,
             index: IndexFilter::Any,
+            attr: None,
             value: ValueFilter::Any
         }
     }
@@ -145,6 +257,25 @@ impl NodeFilter {
         self
     }
 
+    /// Builder function to specify an attribute key.  The node must
+    /// then have an attribute with this key, and the ValueFilter is
+    /// checked against the attribute values instead of the node value.
+    pub fn with_attr<K: Into<Key>>(mut self, key: K) -> Self {
+        self.attr = Some(key.into());
+        self
+    }
+
+    // Check the node value or, if an attribute key is given, the
+    // values of all attributes with this key.
+    fn check_value(&self, node: &Node) -> bool {
+        match &self.attr {
+            None => self.value.check_node(node),
+            Some(key) => node.get_attr_vec(key.as_str())
+                .iter()
+                .any(|value| self.value.check(value))
+        }
+    }
+
     /// Returns true if all nodes of a given memo match the
     /// NodeFilter. Because we have full access to all nodes of the
     /// given Memo, we can check for the index and for the node type.
@@ -162,7 +293,7 @@ impl NodeFilter {
             |(n, _node)| self.index.check(*n)
         ).filter(
             // (4) check for node value
-            |(_n, node)| self.value.check(&node.value)
+            |(_n, node)| self.check_value(node)
         )
         // (5) return true if there is at least one match
             .next().is_some()
@@ -184,7 +315,7 @@ impl NodeFilter {
             move |(n, _node)| self.index.check(*n)
         ).filter(
             // (4) check for node value
-            move |(_n, node)| self.value.check(&node.value)
+            move |(_n, node)| self.check_value(node)
         ).map(
             move |(_n, node)| node
         )
@@ -208,7 +339,7 @@ impl NodeFilter {
             move |(n, (_idx, _node))| self.index.check(*n)
         ).filter(
             // (4) check for node value
-            move |(_n, (_idx, node))| self.value.check(&node.value)
+            move |(_n, (_idx, node))| self.check_value(node)
         ).map(
             move |(_n, (idx, _node))| idx
         )        
@@ -262,6 +393,17 @@ impl MemoFilter {
             .all(|nf: &NodeFilter| nf.check_memo(&memo))
     }
 
+    /// Returns an Iterator that yields all memos matching the filter.
+    /// Only the candidates found in the given index of `memos` are
+    /// checked, see `Index::candidates`.
+    pub fn filter_indexed<'a>(&'a self, memos: &'a [Memo], index: &Index)
+                              -> impl Iterator<Item=&'a Memo> + 'a
+    {
+        index.candidates(self).into_iter()
+            .map(move |position| &memos[position])
+            .filter(move |memo| self.check(memo))
+    }
+
     pub fn select_indices<'a>(&'a self, memo: &'a Memo) -> impl Iterator<Item=usize> + 'a
     {
         // all `node_filters` are OR'ed together, i.e. if any of the
@@ -279,6 +421,7 @@ impl MemoFilter {
 mod tests {
     use crate::filter::*;
     use crate::{Memo, Value};
+    use crate::mql::parse_mql;
 
     fn sample_memo() -> Memo {
         let mut memo = Memo::new("book", "The Lord of the Rings");
@@ -362,6 +505,172 @@ mod tests {
         assert_eq!(nf.index, IndexFilter::Any);
     }
 
+    #[test]
+    fn test_typed_filter() {
+        assert!(ValueFilter::Equals("true".into()).check(&Value::Bool(true)));
+        assert!(ValueFilter::Equals("yes".into()).check(&Value::Bool(true)));
+        assert!(!ValueFilter::Equals("yes".into()).check(&Value::Bool(false)));
+        assert!(ValueFilter::Equals("18".into()).check(&Value::Integer(18)));
+        assert!(ValueFilter::Equals("127.6".into()).check(&Value::infer("127.60")));
+        assert!(ValueFilter::Equals("18.0".into()).check(&Value::Integer(18)));
+        assert!(!ValueFilter::Equals("127.6".into()).check(&Value::infer("127.61")));
+        assert!(ValueFilter::Contains("00".into()).check(&Value::Integer(1008)));
+        assert!(ValueFilter::Contains(".0".into()).check(&Value::from(1.008)));
+        assert!(!ValueFilter::Contains("ru".into()).check(&Value::Bool(true)));
+    }
+
+    #[test]
+    fn test_number_precision() {
+        let amu = Value::infer("9.0121831");
+        assert!(ValueFilter::MoreThan(9.012183).check(&amu));
+        assert!(ValueFilter::LessThan(9.0121832).check(&amu));
+        assert!(!ValueFilter::MoreThan(9.0121831).check(&amu));
+        assert!(ValueFilter::AtLeast(9.0121831).check(&amu));
+
+        let big = Value::Integer(9007199254740993);
+        assert!(ValueFilter::MoreThan(9007199254740990.0).check(&big));
+    }
+
+    #[test]
+    fn test_unit_filter() {
+        let kg_m3 = Unit::parse("kg/m³").unwrap();
+        let filter = ValueFilter::InUnit(Box::new(ValueFilter::MoreThan(5000.0)), kg_m3);
+
+        let node = Node::new("density", 7.87).with_attr("unit", "g/cm³");
+        assert!(filter.check_node(&node));
+        let node = Node::new("density", Value::infer("7.87 g/cm³"));
+        assert!(filter.check_node(&node));
+        assert!(filter.check(&node.value));
+        let node = Node::new("density", Value::infer("2.267 g/cm³"));
+        assert!(!filter.check_node(&node));
+
+        // values without unit or with an incompatible unit never match
+        assert!(!filter.check_node(&Node::new("density", 7870)));
+        assert!(!filter.check_node(&Node::new("density", Value::infer("7870 T"))));
+    }
+
+    #[test]
+    fn test_range_filter() {
+        let density = Value::infer("1.8 - 2.1");
+
+        // overlap
+        assert!(ValueFilter::MoreThan(2.0).check(&density));
+        assert!(ValueFilter::LessThan(2.0).check(&density));
+        assert!(ValueFilter::AtMost(1.8).check(&density));
+        assert!(!ValueFilter::MoreThan(2.1).check(&density));
+        assert!(!ValueFilter::LessThan(1.8).check(&density));
+
+        // fully within
+        let within = |filter| ValueFilter::Within(Box::new(filter));
+        assert!(!within(ValueFilter::MoreThan(2.0)).check(&density));
+        assert!(within(ValueFilter::MoreThan(1.5)).check(&density));
+        assert!(within(ValueFilter::AtLeast(1.8)).check(&density));
+        assert!(within(ValueFilter::MoreThan(1.5)).check(&Value::from(2.0)));
+
+        // ranges with units
+        let kg_m3 = Unit::parse("kg/m³").unwrap();
+        let node = Node::new("density", density).with_attr("unit", "g/cm³");
+        let filter = ValueFilter::InUnit(Box::new(ValueFilter::MoreThan(2000.0)), kg_m3);
+        assert!(filter.check_node(&node));
+        assert!(!within(filter).check_node(&node));
+
+        // the unit of the upper bound applies to the lower bound too
+        let node = Node::new("density", Value::infer("1.8 - 2.1 g/cm³"));
+        let filter = ValueFilter::InUnit(Box::new(ValueFilter::MoreThan(2000.0)), kg_m3);
+        assert!(filter.check_node(&node));
+        let filter = ValueFilter::InUnit(Box::new(ValueFilter::MoreThan(1000.0)), kg_m3);
+        assert!(within(filter).check_node(&node));
+        let filter = ValueFilter::InUnit(Box::new(ValueFilter::LessThan(2000.0)), kg_m3);
+        assert!(filter.check_node(&node));
+        assert!(filter.check(&node.value));
+        let filter = ValueFilter::InUnit(Box::new(ValueFilter::LessThan(1500.0)), kg_m3);
+        assert!(!filter.check_node(&node));
+    }
+
+    #[test]
+    fn test_attr_filter() {
+        let memo = Memo::new("element", "Fe")
+            .with_attr("source", "Wikipedia")
+            .with(("density", 7.87))
+            .with_attr("unit", "g/cm³")
+            .with_attr("source", "Britannica");
+
+        let nf = NodeFilter::default()
+            .with_node_type(NodeType::Header)
+            .with_attr("source");
+        assert!(nf.check_memo(&memo));
+
+        let nf = NodeFilter::default()
+            .with_node_type(NodeType::Header)
+            .with_attr("unit");
+        assert!(!nf.check_memo(&memo));
+
+        // repeated attributes: any of them may match
+        let nf = NodeFilter::default()
+            .with_key(KeyFilter::Equals("density".into()))
+            .with_attr("source")
+            .with_value(ValueFilter::Equals("Britannica".into()));
+        assert!(nf.check_memo(&memo));
+        assert_eq!(nf.select_indices(&memo).collect::<Vec<usize>>(), vec!(1));
+
+        let nf = NodeFilter::default()
+            .with_node_type(NodeType::Header)
+            .with_attr("source")
+            .with_value(ValueFilter::Equals("Britannica".into()));
+        assert!(!nf.check_memo(&memo));
+    }
+
+    #[test]
+    fn test_ref_filter() {
+        let author = Value::Ref("author".into(), "J.R.R. Tolkien".into());
+        assert!(ValueFilter::Equals("J.R.R. Tolkien".into()).check(&author));
+        assert!(ValueFilter::Equals("@author J.R.R. Tolkien".into()).check(&author));
+        assert!(!ValueFilter::Equals("author".into()).check(&author));
+        assert!(ValueFilter::Contains("Tolkien".into()).check(&author));
+        assert!(!ValueFilter::Contains("author".into()).check(&author));
+    }
+
+    #[test]
+    fn test_date_filter() {
+        let date = |s: &str| s.parse::<Date>().unwrap();
+        let birthday = Value::infer("1892-01-03");
+
+        assert!(ValueFilter::Before(date("1900-01-01")).check(&birthday));
+        assert!(!ValueFilter::Before(date("1892-01-03")).check(&birthday));
+        assert!(ValueFilter::After(date("1892-01-02")).check(&birthday));
+        assert!(!ValueFilter::After(date("1892-01-03")).check(&birthday));
+        assert!(ValueFilter::SameDate(date("1892-01-03")).check(&birthday));
+
+        let finished = Value::infer("2021-01-24T14:30");
+        assert!(ValueFilter::SameDate(date("2021-01-24")).check(&finished));
+
+        // partial dates cover a whole year or month
+        assert!(ValueFilter::SameDate(date("1892")).check(&birthday));
+        assert!(ValueFilter::SameDate(date("1892-01")).check(&birthday));
+        assert!(!ValueFilter::Before(date("1892")).check(&birthday));
+        assert!(ValueFilter::Before(date("1892-02")).check(&birthday));
+        assert!(ValueFilter::After(date("1891")).check(&birthday));
+        assert!(!ValueFilter::After(date("1892")).check(&birthday));
+
+        let discovery = Value::from("1808-06");
+        assert!(ValueFilter::SameDate(date("1808-06-30")).check(&discovery));
+        assert!(ValueFilter::Before(date("1808-07-01")).check(&discovery));
+
+        // a year on its own is inferred as number, but still a date
+        let memo = Memo::new("element", "H").with(("discovery", Value::infer("1766")));
+        for mql in &["discovery>>1700", "discovery==1766", "discovery<<1800", "discovery==1766-05"] {
+            assert!(parse_mql(mql).unwrap().check(&memo), "{}", mql);
+        }
+        for mql in &["discovery>>1766", "discovery<<1766", "discovery==1767"] {
+            assert!(!parse_mql(mql).unwrap().check(&memo), "{}", mql);
+        }
+        assert!(!ValueFilter::SameDate(date("0042")).check(&Value::Integer(42)));
+
+        let text = Value::from("a long time ago");
+        assert!(!ValueFilter::Before(date("2021-01-24")).check(&text));
+        assert!(!ValueFilter::After(date("2021-01-24")).check(&text));
+    }
+
     #[test]
     fn test_index_range() {
         let filter = IndexFilter::Range(2, 5);
@@ -0,0 +1,370 @@
+//! An inverted index to narrow down the memos matching a filter.
+//!
+//! The index refers to memos by their position in a list of memos,
+//! e.g. the memos of a `Database`.  It maps
+//!
+//! - each node key to the memos that contain a node with this key,
+//! - each node key to the numeric values of these nodes, sorted, and
+//! - each text token (lowercase, split at non-alphanumeric
+//!   characters) of the node values to the memos containing it.
+//!
+//! `Index::candidates` returns the memos that may match a
+//! `MemoFilter`.  This is a superset of the matching memos, so the
+//! filter must still be checked for each candidate, but usually far
+//! fewer memos need to be checked.  Conditions that cannot be looked
+//! up, e.g. on dates or attributes, only narrow down by key.
+//!
+
+use std::collections::HashMap;
+use std::convert::TryFrom;
+
+use crate::{Memo, Key, Value};
+use crate::filter::{MemoFilter, NodeFilter, KeyFilter, ValueFilter};
+
+#[derive(Debug, Default, PartialEq)]
+pub struct Index {
+    len: usize, // number of indexed memos
+    keys: HashMap<Key, Vec<usize>>,
+    numbers: HashMap<Key, Vec<(f64, usize)>>, // sorted by number, then position
+    tokens: HashMap<String, Vec<usize>>
+}
+
+// Splits the given text into lowercase tokens.
+fn tokenize(text: &str) -> impl Iterator<Item=String> + '_ {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|token| !token.is_empty())
+        .map(|token| token.to_lowercase())
+}
+
+// Adds `position` to the sorted list of positions, unless it is
+// already contained.  Usually, positions are added in ascending
+// order, i.e. at the end.
+fn add_position(positions: &mut Vec<usize>, position: usize) {
+    match positions.last() {
+        Some(&last) if last >= position => {
+            if let Err(at) = positions.binary_search(&position) {
+                positions.insert(at, position);
+            }
+        },
+        _ => positions.push(position)
+    }
+}
+
+// Removes `position` from the lists of positions of the given key,
+// and the key itself if no positions are left.
+fn remove_position<K>(map: &mut HashMap<K, Vec<usize>>, key: &K, position: usize)
+where K: std::hash::Hash + Eq
+{
+    if let Some(positions) = map.get_mut(key) {
+        if let Ok(at) = positions.binary_search(&position) {
+            positions.remove(at);
+        }
+        if positions.is_empty() {
+            map.remove(key);
+        }
+    }
+}
+
+// Sorts the numbers of a key, equal numbers by position.
+fn sort_numbers(numbers: &mut [(f64, usize)]) {
+    numbers.sort_by(|(x, a), (y, b)| x.total_cmp(y).then(a.cmp(b)));
+}
+
+// Inserts the number `x` of the memo at `position` at its sorted
+// place, see `sort_numbers`.
+fn insert_number(numbers: &mut Vec<(f64, usize)>, x: f64, position: usize) {
+    let at = numbers.partition_point(|(y, other)| y.total_cmp(&x).then(other.cmp(&position)).is_lt());
+    numbers.insert(at, (x, position));
+}
+
+// Returns the positions of the given entries, sorted and without
+// duplicates.
+fn positions(entries: &[(f64, usize)]) -> Vec<usize> {
+    let mut positions: Vec<usize> = entries.iter().map(|(_, position)| *position).collect();
+    positions.sort_unstable();
+    positions.dedup();
+    positions
+}
+
+// Returns the positions contained in both sorted lists.
+fn intersect(a: Vec<usize>, b: &[usize]) -> Vec<usize> {
+    a.into_iter().filter(|position| b.binary_search(position).is_ok()).collect()
+}
+
+impl Index {
+    /// Constructs the index of the given memos.
+    pub fn new(memos: &[Memo]) -> Self {
+        let mut index = Index::default();
+        for memo in memos {
+            index.add(memo, false);
+        }
+        // sorting once is much faster than inserting each number at
+        // its sorted position
+        index.numbers.values_mut().for_each(|numbers| sort_numbers(numbers));
+        index
+    }
+
+    /// Adds the given memo, which gets the next position.
+    pub fn push(&mut self, memo: &Memo) {
+        self.add(memo, true);
+    }
+
+    /// Removes the entries of the given memo at `position`, e.g.
+    /// before the memo is changed.  The position stays valid, see
+    /// `add_entries`.
+    pub fn remove_entries(&mut self, position: usize, memo: &Memo) {
+        for node in memo.nodes() {
+            remove_position(&mut self.keys, &node.key, position);
+            if let Some(numbers) = self.numbers.get_mut(&node.key) {
+                numbers.retain(|(_, other)| *other != position);
+                if numbers.is_empty() {
+                    self.numbers.remove(&node.key);
+                }
+            }
+            for token in tokenize(&node.value.to_string()) {
+                remove_position(&mut self.tokens, &token, position);
+            }
+        }
+    }
+
+    /// Adds the entries of the given memo at an existing `position`,
+    /// e.g. after the memo has been changed.
+    pub fn add_entries(&mut self, position: usize, memo: &Memo) {
+        self.add_at(position, memo, true);
+    }
+
+    // Adds the given memo at the next position, see `add_at`.
+    fn add(&mut self, memo: &Memo, sorted: bool) {
+        let position = self.len;
+        self.len += 1;
+        self.add_at(position, memo, sorted);
+    }
+
+    // Adds the entries of the given memo at `position`.  The numbers
+    // are inserted at their sorted place if `sorted` is true,
+    // otherwise they are appended and must be sorted afterwards.
+    fn add_at(&mut self, position: usize, memo: &Memo, sorted: bool) {
+        for node in memo.nodes() {
+            add_position(self.keys.entry(node.key.clone()).or_default(), position);
+
+            let bounds = match &node.value {
+                Value::Range(from, to, _) => vec!(from.as_ref(), to.as_ref()),
+                value => vec!(value)
+            };
+            for value in bounds {
+                if let Ok(x) = f64::try_from(value) {
+                    if !x.is_nan() {
+                        let numbers = self.numbers.entry(node.key.clone()).or_default();
+                        match sorted {
+                            true => insert_number(numbers, x, position),
+                            false => numbers.push((x, position))
+                        }
+                    }
+                }
+            }
+
+            for token in tokenize(&node.value.to_string()) {
+                add_position(self.tokens.entry(token).or_default(), position);
+            }
+        }
+    }
+
+    /// Returns the number of indexed memos.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns true if no memos are indexed.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    // Returns the memos having a node that matches the key filter,
+    // or None if the key filter cannot be looked up.
+    fn key_candidates(&self, filter: &KeyFilter) -> Option<Vec<usize>> {
+        match filter {
+            KeyFilter::Equals(key) => Some(self.keys.get(key).cloned().unwrap_or_default()),
+            KeyFilter::StartsWith(prefix) => {
+                let mut positions: Vec<usize> = self.keys.iter()
+                    .filter(|(key, _)| key.starts_with(prefix.as_str()))
+                    .flat_map(|(_, positions)| positions.iter().copied())
+                    .collect();
+                positions.sort_unstable();
+                positions.dedup();
+                Some(positions)
+            },
+            _ => None
+        }
+    }
+
+    // Returns the memos having a node with the given key whose value
+    // may match the value filter, or None if the value filter cannot
+    // be looked up.
+    fn value_candidates(&self, key: Option<&Key>, filter: &ValueFilter) -> Option<Vec<usize>> {
+        match filter {
+            ValueFilter::Within(inner) => self.value_candidates(key, inner),
+            ValueFilter::LessThan(_) | ValueFilter::AtMost(_) |
+            ValueFilter::MoreThan(_) | ValueFilter::AtLeast(_) => {
+                let numbers = match self.numbers.get(key?) {
+                    Some(numbers) => numbers.as_slice(),
+                    None => return Some(vec!())
+                };
+                // the numbers are sorted, so the matching ones are
+                // either at the beginning or at the end
+                let (start, end) = match *filter {
+                    ValueFilter::LessThan(x) => (0, numbers.partition_point(|(y, _)| *y < x)),
+                    ValueFilter::AtMost(x) => (0, numbers.partition_point(|(y, _)| *y <= x)),
+                    ValueFilter::MoreThan(x) => (numbers.partition_point(|(y, _)| *y <= x), numbers.len()),
+                    ValueFilter::AtLeast(x) => (numbers.partition_point(|(y, _)| *y < x), numbers.len()),
+                    _ => (0, 0)
+                };
+                Some(positions(&numbers[start..end]))
+            },
+            // `yes` and `no` also match booleans, see ValueFilter::check
+            ValueFilter::Equals(x) if Value::parse_bool(x).is_some() => None,
+            ValueFilter::Equals(x) => {
+                let token = tokenize(x).next()?;
+                let by_token = self.tokens.get(&token).cloned().unwrap_or_default();
+                // numbers match numerically, e.g. `127.6` and `127.60`
+                let x = match x.parse::<f64>() {
+                    Ok(x) => x,
+                    Err(_) => return Some(by_token)
+                };
+                let numbers = self.numbers.get(key?).map(|n| n.as_slice()).unwrap_or_default();
+                let start = numbers.partition_point(|(y, _)| *y < x);
+                let end = numbers.partition_point(|(y, _)| *y <= x);
+                let mut positions = positions(&numbers[start..end]);
+                positions.extend(by_token);
+                positions.sort_unstable();
+                positions.dedup();
+                Some(positions)
+            },
+            ValueFilter::Contains(x) => {
+                // the longest token of `x` is part of a token of the value
+                let token = tokenize(x).max_by_key(|token| token.len())?;
+                let mut positions: Vec<usize> = self.tokens.iter()
+                    .filter(|(other, _)| other.contains(token.as_str()))
+                    .flat_map(|(_, positions)| positions.iter().copied())
+                    .collect();
+                positions.sort_unstable();
+                positions.dedup();
+                Some(positions)
+            },
+            _ => None
+        }
+    }
+
+    // Returns the memos that may match the node filter, or None if
+    // the node filter cannot be looked up.
+    fn node_candidates(&self, filter: &NodeFilter) -> Option<Vec<usize>> {
+        let by_key = self.key_candidates(&filter.key);
+        let key = match &filter.key {
+            KeyFilter::Equals(key) => Some(key),
+            _ => None
+        };
+        // attribute values are not indexed
+        let by_value = match filter.attr {
+            None => self.value_candidates(key, &filter.value),
+            Some(_) => None
+        };
+        match (by_key, by_value) {
+            (Some(a), Some(b)) => Some(intersect(a, &b)),
+            (a, b) => a.or(b)
+        }
+    }
+
+    /// Returns the positions of all memos that may match the given
+    /// filter, in ascending order.
+    pub fn candidates(&self, filter: &MemoFilter) -> Vec<usize> {
+        filter.node_filters.iter()
+            .filter_map(|nf| self.node_candidates(nf))
+            .reduce(|a, b| intersect(a, &b))
+            .unwrap_or_else(|| (0..self.len).collect())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mql::parse_mql;
+    use crate::parser::read_from_str;
+
+    // Checks that the candidates contain all matching memos and
+    // returns the number of candidates.
+    fn check(memos: &[Memo], index: &Index, mql: &str) -> usize {
+        let filter = parse_mql(mql).unwrap();
+        let candidates = index.candidates(&filter);
+        for (position, memo) in memos.iter().enumerate() {
+            if filter.check(memo) {
+                assert!(candidates.contains(&position), "{}: missing {}", mql, memo.title());
+            }
+        }
+        candidates.len()
+    }
+
+    #[test]
+    fn candidates() {
+        let dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR"));
+        let input = std::fs::read_to_string(dir.join("data/periodic.mr")).unwrap();
+        let memos = read_from_str(&input).unwrap();
+        let index = Index::new(&memos);
+        assert_eq!(index.len(), memos.len());
+        assert!(index.numbers["number"].windows(2).all(|w| w[0].0 <= w[1].0));
+
+        // adding the memos one by one results in the same index
+        let mut pushed = Index::default();
+        memos.iter().for_each(|memo| pushed.push(memo));
+        assert_eq!(pushed.numbers, index.numbers);
+        assert_eq!(pushed.tokens, index.tokens);
+        // also if the numbers are not pushed in ascending order
+        let mut reversed = read_from_str(&input).unwrap();
+        reversed.reverse();
+        let mut pushed = Index::default();
+        reversed.iter().for_each(|memo| pushed.push(memo));
+        assert_eq!(pushed, Index::new(&reversed));
+
+        // numeric conditions and single words are looked up exactly
+        let matches = |mql: &str| memos.iter().filter(|&memo| parse_mql(mql).unwrap().check(memo)).count();
+        for mql in ["name=iron", "number>=80,number<=90", "number<3", "amu>200"].iter() {
+            assert_eq!(check(&memos, &index, mql), matches(mql), "{}", mql);
+        }
+        assert_eq!(check(&memos, &index, "name=iron"), 1);
+        assert_eq!(check(&memos, &index, "nonexistent"), 0);
+        assert!(check(&memos, &index, "electrons~Ar") < check(&memos, &index, "electrons"));
+
+        let queries = ["density>5", "density*<2", "density<1", "density>5000 kg/m³",
+                       "discovery+date<1700", "@element=Fe", "group=1", "name~ium",
+                       ".amu>58", "@+id", "+unit=g/cm³", "discovery>>1800",
+                       "state=liquid", "name~Hyd", "electrons~[Ar] 3d", "radioactive=yes"];
+        for mql in queries.iter() {
+            check(&memos, &index, mql);
+        }
+    }
+
+    #[test]
+    fn ranges_and_quantities() {
+        let memos = vec!(
+            Memo::new("element", "C").with(("density", Value::infer("1.8 - 2.1"))),
+            Memo::new("element", "Fe").with(("density", Value::infer("7.87 g/cm³"))),
+            Memo::new("element", "X").with(("density", "unknown"))
+        );
+        let index = Index::new(&memos);
+        assert_eq!(check(&memos, &index, "density>2"), 2);
+        assert_eq!(check(&memos, &index, "density<2"), 1);
+        assert_eq!(check(&memos, &index, "density*>2"), 2);
+        assert_eq!(check(&memos, &index, "density>8"), 0);
+        assert_eq!(check(&memos, &index, "density~unk"), 1);
+        assert_eq!(check(&memos, &index, "density"), 3);
+        assert_eq!(check(&memos, &index, "density>>2021"), 3);
+        assert_eq!(check(&memos, &index, "@element"), 3);
+
+        let memos = vec!(
+            Memo::new("element", "Te").with(("amu", Value::infer("127.60"))),
+            Memo::new("element", "I").with(("amu", Value::infer("126.90447")))
+        );
+        let index = Index::new(&memos);
+        assert_eq!(check(&memos, &index, "amu=127.6"), 1);
+        assert_eq!(check(&memos, &index, "amu=127.60"), 1);
+    }
+}
@@ -4,14 +4,26 @@
 #[macro_use] extern crate pest_derive;
 
 pub mod memo;
+pub mod database;
+pub mod index;
 pub mod node;
 pub mod value;
+pub mod date;
+pub mod unit;
 pub mod sample;
 pub mod parser;
+pub mod directory;
+pub mod writer;
 pub mod filter;
 pub mod mql;
 pub mod prelude;
 pub mod display;
+pub mod reference;
+pub mod diff;
+pub mod prototype;
+#[cfg(feature = "serde")]
+pub mod serialize;
+pub mod location;
 
 use memo::Memo;
 use node::Node;
@@ -0,0 +1,93 @@
+//! Source locations of memos and nodes.
+//!
+//! The parser records for each node the Span of text it was read
+//! from, i.e. line and column of its first and last character.  The
+//! path of the file is stored once per memo, so that the location of
+//! a node is given by `Memo::location_of`, e.g.
+//! `data/periodic.mr:112:1`.
+//!
+//! Nodes that are created in code have no location.  Locations are
+//! not taken into account when comparing nodes or memos.
+//!
+
+use pest::iterators::Pair;
+use pest::RuleType;
+
+use std::path::Path;
+
+/// Lines and columns of the text a node was read from.  Both lines
+/// and columns start with 1, the end is inclusive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Span {
+    pub line: usize,
+    pub column: usize,
+    pub end_line: usize,
+    pub end_column: usize
+}
+
+impl Span {
+    /// Returns the Span of the given pest Pair.
+    pub fn from_pair<R: RuleType>(pair: &Pair<R>) -> Span {
+        let (line, column) = pair.line_col();
+        // the end is computed from the text of the pair, which is
+        // much cheaper than looking up the end position in the input
+        let text = pair.as_str().trim_end_matches(['\r', '\n']);
+        let (end_line, end_column) = match text.rfind('\n') {
+            Some(pos) => (line + text.matches('\n').count(),
+                          text[pos + 1..].chars().count()),
+            None => (line, column + text.chars().count().max(1) - 1)
+        };
+        Span { line, column, end_line, end_column }
+    }
+}
+
+impl std::fmt::Display for Span {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
+/// Location of a memo or node, given by file path (if known) and Span.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Location<'a> {
+    pub path: Option<&'a Path>,
+    pub span: Span
+}
+
+impl std::fmt::Display for Location<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.path {
+            Some(path) => write!(f, "{}:{}", path.display(), self.span),
+            None => write!(f, "{}", self.span)
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::read_from_str;
+    use crate::Memo;
+
+    #[test]
+    fn node_spans() {
+        let input = "# sample\n@element C\n.number 6\n.doc<<EOF\nfirst\nsecond\nEOF\n\n@element N\n.colors, red, blue\n+tag x";
+        let memos = read_from_str(input).unwrap();
+        let span = |memo: usize, node: usize| memos[memo].get_by_index(node).unwrap().location.unwrap();
+
+        assert_eq!(span(0, 0), Span { line: 2, column: 1, end_line: 2, end_column: 10 });
+        assert_eq!(span(0, 1), Span { line: 3, column: 1, end_line: 3, end_column: 9 });
+        assert_eq!(span(0, 2), Span { line: 4, column: 1, end_line: 7, end_column: 3 });
+        assert_eq!(span(1, 0).line, 9);
+        // all nodes of a multi-value line share the same span
+        assert_eq!(span(1, 1), Span { line: 10, column: 1, end_line: 11, end_column: 6 });
+        assert_eq!(span(1, 2), span(1, 1));
+
+        let mut memo = read_from_str(input).unwrap().remove(1);
+        assert_eq!(memo.location().unwrap().to_string(), "9:1");
+        memo.set_path("data/sample.mr");
+        assert_eq!(memo.location_of(memo.last()).unwrap().to_string(), "data/sample.mr:10:1");
+        assert_eq!(Memo::new("element", "O").location(), None);
+    }
+}
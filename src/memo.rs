@@ -11,23 +11,62 @@
 //! which are called data nodes.
 //!
 //! An Node consists of a Key and a Value, with optional attributes.
-//! Attributes are stored as a HashMap of Key/Value pairs.  Both
+//! Attributes are stored as an ordered list of Key/Value pairs.  Both
 //! the header node and the data nodes can have attributes.
 //!
+//! Comments in a file belong to the node that follows them.  The
+//! comments before a memo are therefore stored in its header node.
+//! Comments at the end of a file are stored in the last memo.
+//!
 
 use crate::value::{Key, Value};
 use crate::node::Node;
+use crate::location::Location;
+
+use std::path::{Path, PathBuf};
+
+pub type MemoId = String;
+
+/// Returns an id derived from collection and title of a memo, which
+/// is stable across program versions and platforms.  It is the
+/// 64-bit FNV-1a hash of `collection` and `title`, as hex string.
+pub fn generate_id(collection: &str, title: &str) -> MemoId {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    // the zero byte separates collection and title
+    for byte in collection.bytes().chain(std::iter::once(0)).chain(title.bytes()) {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+    }
+    format!("{:016x}", hash)
+}
 
-use std::hash::{Hash, Hasher};
-use std::collections::hash_map::DefaultHasher;
+/// Error returned by `Memo::insert` if the given index is not a
+/// valid position for a data node.
+#[derive(Debug, Clone, PartialEq)]
+pub struct IndexError {
+    pub index: usize,
+    pub len: usize // number of nodes, including the header node
+}
+
+impl std::fmt::Display for IndexError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "node index {} is out of range 1..={}", self.index, self.len)
+    }
+}
 
-pub type MemoId = u64;
+impl std::error::Error for IndexError {}
 
 /// A Memo consists of multiple nodes, the first one being the header
 /// node and the subsequent ones being the data nodes.
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize),
+           serde(try_from = "crate::serialize::MemoRepr"))]
 pub struct Memo {
     nodes: Vec<Node>,
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    path: Option<PathBuf>, // file the memo was read from
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Vec::is_empty"))]
+    trailing_comments: Vec<String> // comment lines after the last memo of a file
 }
 
 impl Memo {
@@ -44,6 +83,8 @@ impl Memo {
     {
         Memo {
             nodes: vec![Node::new(collection, title)],
+            path: None,
+            trailing_comments: Vec::new()
         }
     }
 
@@ -72,7 +113,7 @@ impl Memo {
         // TODO: won't work if Node.attr is not public anymore
         // maybe make Node.attr pub only for the crate
         let index = self.nodes.len() - 1;
-        self.nodes[index].attrs.insert(key.into(), value.into());
+        self.nodes[index].push_attr(key, value);
         self
     }
     
@@ -91,12 +132,85 @@ impl Memo {
         &self.nodes[0]
     }
 
-    /// Returns unique header id
+    /// Returns the comment lines written before the Memo.
+    pub fn comments(&self) -> &[String] {
+        self.header().comments()
+    }
+
+    /// Returns the comment lines written after the Memo, if it is
+    /// the last one of a file.
+    pub fn trailing_comments(&self) -> &[String] {
+        &self.trailing_comments
+    }
+
+    /// Sets the comment lines written after the Memo.
+    pub fn set_trailing_comments(&mut self, comments: Vec<String>) {
+        self.trailing_comments = comments;
+    }
+
+    /// Returns mutable reference to header node.
+    pub fn header_mut(&mut self) -> &mut Node {
+        &mut self.nodes[0]
+    }
+
+    /// Sets Memo collection (key of header node).
+    pub fn set_collection<K: Into<Key>>(&mut self, collection: K) {
+        self.header_mut().key = collection.into();
+    }
+
+    /// Sets Memo title (value of header node).  Note that this
+    /// changes the id of a Memo without explicit id.
+    pub fn set_title<V: Into<Value>>(&mut self, title: V) {
+        self.header_mut().value = title.into();
+    }
+
+    /// Returns path of the file the Memo was read from.
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// Sets path of the file the Memo was read from.
+    pub fn set_path<P: Into<PathBuf>>(&mut self, path: P) {
+        self.path = Some(path.into());
+    }
+
+    /// Returns the location of the Memo, i.e. of its header node.
+    pub fn location(&self) -> Option<Location<'_>> {
+        self.location_of(self.header())
+    }
+
+    /// Returns the location of the given node of the Memo, or None
+    /// if the node was not read from a file.
+    pub fn location_of(&self, node: &Node) -> Option<Location<'_>> {
+        Some(Location { path: self.path(), span: node.location? })
+    }
+
+    /// Returns the id of the Memo, which is given by the header
+    /// attribute `+id`.  Without such an attribute, the id is derived
+    /// from collection and title, see `generate_id`, and therefore
+    /// changes if the title changes.
     pub fn id(&self) -> MemoId {
-        let mut s = DefaultHasher::new();
-        self.collection().hash(&mut s);
-        self.title().hash(&mut s);
-        s.finish()
+        self.explicit_id()
+            .unwrap_or_else(|| generate_id(&self.collection(), &self.title()))
+    }
+
+    /// Returns the id given by the header attribute `+id`, if any.
+    pub fn explicit_id(&self) -> Option<MemoId> {
+        self.header().get_attr("id").map(|id| id.to_string())
+    }
+
+    /// Makes the id of the Memo persistent by adding the header
+    /// attribute `+id` with the generated id, unless the Memo
+    /// already has an explicit id.  Returns the id.
+    pub fn ensure_id(&mut self) -> MemoId {
+        match self.explicit_id() {
+            Some(id) => id,
+            None => {
+                let id = self.id();
+                self.header_mut().push_attr("id", id.as_str());
+                id
+            }
+        }
     }
 
     /// Returns iterator to all nodes (header and data)
@@ -148,6 +262,107 @@ impl Memo {
         self.nodes.get_mut(index).unwrap()
     }
 
+    /// Returns iterator to mutable data nodes.
+    pub fn data_mut(&mut self) -> impl Iterator<Item=&mut Node> {
+        self.nodes[1..].iter_mut()
+    }
+
+    /// Returns mutable reference to the first data node that matches
+    /// the given key.
+    pub fn get_mut<K: Into<Key>>(&mut self, key: K) -> Option<&mut Node> {
+        let key = key.into();
+        self.data_mut().find(|n| n.key == key)
+    }
+
+    /// Returns mutable reference to node with the given index.
+    pub fn get_by_index_mut(&mut self, idx: usize) -> Option<&mut Node> {
+        self.nodes.get_mut(idx)
+    }
+
+    /// Sets the value of the first data node with the given key and
+    /// returns the previous value.  The attributes of the node are
+    /// kept.  If there is no such node, a new one is added.
+    pub fn set<K, V>(&mut self, key: K, value: V) -> Option<Value>
+    where K: Into<Key>, V: Into<Value>
+    {
+        let key = key.into();
+        match self.get_mut(key.as_str()) {
+            Some(node) => Some(std::mem::replace(&mut node.value, value.into())),
+            None => {
+                self.push(Node::new(key, value));
+                None
+            }
+        }
+    }
+
+    /// Inserts the given node at position `idx`, shifting all nodes
+    /// after it.  As the header node has index 0, data nodes are
+    /// inserted at index 1 to `data_count() + 1`.  Fails if `idx` is
+    /// outside of this range.
+    pub fn insert<N: Into<Node>>(&mut self, idx: usize, node: N) -> Result<(), IndexError> {
+        if idx == 0 || idx > self.nodes.len() {
+            return Err(IndexError { index: idx, len: self.nodes.len() });
+        }
+        self.nodes.insert(idx, node.into());
+        Ok(())
+    }
+
+    /// Replaces the data node at position `idx` with the given node
+    /// and returns the previous node, or None if there is no data
+    /// node at this position.
+    pub fn replace<N: Into<Node>>(&mut self, idx: usize, node: N) -> Option<Node> {
+        if idx == 0 || idx >= self.nodes.len() {
+            return None;
+        }
+        Some(std::mem::replace(&mut self.nodes[idx], node.into()))
+    }
+
+    /// Removes the data node at position `idx` and returns it, or
+    /// None if there is no data node at this position.
+    pub fn remove(&mut self, idx: usize) -> Option<Node> {
+        if idx == 0 || idx >= self.nodes.len() {
+            return None;
+        }
+        Some(self.nodes.remove(idx))
+    }
+
+    /// Replaces all data nodes with the given nodes and returns the
+    /// previous data nodes.  The header node is kept.
+    pub fn replace_data(&mut self, nodes: Vec<Node>) -> Vec<Node> {
+        let data = self.nodes.split_off(1);
+        self.nodes.extend(nodes);
+        data
+    }
+
+    /// Removes all data nodes with the given key and returns them.
+    pub fn remove_key<K: Into<Key>>(&mut self, key: K) -> Vec<Node> {
+        let key = key.into();
+        let mut data = self.nodes.split_off(1);
+        let mut removed = Vec::new();
+        for node in data.drain(..) {
+            if node.key == key {
+                removed.push(node);
+            } else {
+                self.nodes.push(node);
+            }
+        }
+        removed
+    }
+
+    /// Renames all data nodes with key `from` to `to` and returns the
+    /// number of renamed nodes.
+    pub fn rename_key<K1, K2>(&mut self, from: K1, to: K2) -> usize
+    where K1: Into<Key>, K2: Into<Key>
+    {
+        let (from, to) = (from.into(), to.into());
+        let mut count = 0;
+        for node in self.data_mut().filter(|n| n.key == from) {
+            node.key = to.clone();
+            count += 1;
+        }
+        count
+    }
+
     /// Returns reference to the first data node that matches the given key.
     pub fn get<K: Into<Key>>(&self, key: K) -> Option<&Node> {
         let key = key.into();
@@ -168,6 +383,16 @@ impl Memo {
             .collect::<Vec<&Node>>()
     }
 
+    /// Returns all data nodes of the given group, including nested
+    /// groups, e.g. `address/city` and `address/phone/home` for the
+    /// group `address`.
+    pub fn get_group<K: Into<Key>>(&self, group: K) -> Vec<&Node> {
+        let prefix = format!("{}/", group.into().trim_end_matches('/'));
+        self.data()
+            .filter(|n| n.key.starts_with(&prefix))
+            .collect::<Vec<&Node>>()
+    }
+
     /// Returns true if the Memo contains at least one data node with
     /// the given key.
     pub fn contains_key<K: Into<Key>>(&self, key: K) -> bool {
@@ -187,23 +412,7 @@ impl Memo {
 
 impl std::fmt::Display for Memo {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        // print header
-        let prefix = "@";
-        let header = self.header();
-        writeln!(f, "{}{} {}", prefix, header.key, header.value)?;
-        for (key, value) in header.attrs.iter() {
-            writeln!(f, "+{} {}", key, value)?;
-        }
-        
-        // print data nodes
-        let prefix = ".";
-        for node in &self.nodes {
-            writeln!(f, "{}{} {}", prefix, node.key, node.value)?;
-            for (key, value) in node.attrs.iter() {
-                writeln!(f, "+{} {}", key, value)?;
-            }
-        }
-        Ok(())
+        crate::writer::write_memo(f, self)
     }
 }
 
@@ -211,6 +420,8 @@ impl std::fmt::Display for Memo {
 /// The NodeType is determined by the position inside the Memo.
 /// It is therefore defined in `memo.rs` and not in `node.rs`.
 #[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize),
+           serde(rename_all = "lowercase"))]
 pub enum NodeType {
     Header,
     Data,
@@ -271,6 +482,95 @@ mod tests {
         }
     }
 
+    #[test]
+    fn edit_header() {
+        let mut memo = sample_memo();
+        let id = memo.id();
+        memo.set_collection("novel");
+        memo.set_title("The Hobbit");
+        assert_eq!(memo.collection(), "novel");
+        assert_eq!(memo.title(), "The Hobbit");
+        assert_ne!(memo.id(), id);
+        assert_eq!(memo.data_count(), 3);
+    }
+
+    #[test]
+    fn edit_data() {
+        let mut memo = sample_memo();
+        assert_eq!(memo.set("author", "Tolkien"), Some(Value::from("J.R.R. Tolkien")));
+        assert_eq!(memo.set("year", 1954), None);
+        assert_eq!(memo.get("year").unwrap().value, Value::from(1954));
+        assert_eq!(memo.data_count(), 4);
+
+        memo.get_mut("character").unwrap().push_attr("race", "Hobbit");
+        assert_eq!(memo.get("character").unwrap().get_attr("race"), Some(&Value::from("Hobbit")));
+        assert!(memo.get_mut("book").is_none());
+
+        assert_eq!(memo.insert(1, Node::new("series", "Middle-earth")), Ok(()));
+        assert_eq!(memo.get_by_index(1).unwrap().key, "series");
+        assert_eq!(memo.insert(0, Node::new("author", "Tolkien")),
+                   Err(IndexError { index: 0, len: 6 }));
+        let error = memo.insert(memo.data_count() + 2, Node::new("author", "Tolkien")).unwrap_err();
+        assert_eq!(error.to_string(), "node index 7 is out of range 1..=6");
+        assert_eq!(memo.data_count(), 5);
+        let old = memo.replace(1, Node::new("publisher", "Allen & Unwin")).unwrap();
+        assert_eq!(old.key, "series");
+        assert!(memo.replace(0, Node::new("series", "x")).is_none());
+        assert!(memo.replace(10, Node::new("series", "x")).is_none());
+
+        assert_eq!(memo.rename_key("character", "person"), 2);
+        assert!(!memo.contains_key("character"));
+        let removed = memo.remove_key("person");
+        assert_eq!(removed.len(), 2);
+        assert_eq!(removed[1].value, Value::from("Samweis Gamdschie"));
+        assert_eq!(memo.collection(), "book");
+
+        let keys: Vec<&str> = memo.data().map(|node| node.key.as_str()).collect();
+        assert_eq!(keys, vec!("publisher", "author", "year"));
+        assert_eq!(memo.remove(2).unwrap().key, "author");
+        assert!(memo.remove(0).is_none());
+        assert!(memo.remove(3).is_none());
+        assert_eq!(memo.data_count(), 2);
+
+        let data = memo.replace_data(vec!(Node::new("title", "The Hobbit")));
+        assert_eq!(data.len(), 2);
+        assert_eq!(memo.data_count(), 1);
+        assert_eq!(memo.header().value, Value::from("The Lord of the Rings"));
+    }
+
+    #[test]
+    fn get_group() {
+        let memo = Memo::new("contact", "Frodo")
+            .with(("address/street", "Bagshot Row"))
+            .with(("address/city", "Hobbiton"))
+            .with(("address/phone/home", "123"))
+            .with(("addressee", "Bilbo"));
+        let keys = |group| memo.get_group(group).iter().map(|n| n.key.clone()).collect::<Vec<_>>();
+        assert_eq!(keys("address"), vec!("address/street", "address/city", "address/phone/home"));
+        assert_eq!(keys("address/"), keys("address"));
+        assert_eq!(keys("address/phone"), vec!("address/phone/home"));
+        assert!(keys("phone").is_empty());
+    }
+
+    #[test]
+    fn memo_id() {
+        let mut memo = sample_memo();
+        assert_eq!(memo.explicit_id(), None);
+        assert_eq!(memo.id(), generate_id("book", "The Lord of the Rings"));
+        assert_eq!(memo.id().len(), 16);
+        assert_ne!(generate_id("book", "The Hobbit"), generate_id("bookThe", " Hobbit"));
+        // FNV-1a is fixed, so ids must not change between versions
+        assert_eq!(generate_id("", ""), "af63bd4c8601b7df");
+
+        let id = memo.ensure_id();
+        assert_eq!(memo.explicit_id(), Some(id.clone()));
+        assert_eq!(memo.ensure_id(), id);
+        assert_eq!(memo.header().get_attr_vec("id").len(), 1);
+
+        let memo = Memo::new("author", "J.R.R. Tolkien").with_attr("id", "tolkien");
+        assert_eq!(memo.id(), "tolkien");
+    }
+
     #[test]
     fn partial_eq() {
         let mut memo1 = Memo::new("book", "The Lord of the Rings")
@@ -10,6 +10,8 @@ use crate::filter::{
 };
 
 use crate::memo::NodeType;
+use crate::date::Date;
+use crate::unit::Unit;
 
 use log::*;
 
@@ -27,7 +29,7 @@ pub fn parse_mql(input: &'_ str) -> ParseResult<MemoFilter>
         for pair in pairs {
             match pair.as_rule() {
                 Rule::condition => {
-                    let condition = parse_condition(pair).unwrap();
+                    let condition = parse_condition(pair)?;
                     filter.add(condition);
                 },                
                 _ => {}
@@ -45,6 +47,8 @@ fn parse_condition(pair: Pair<Rule>) -> ParseResult<NodeFilter>
 
     let mut operator: Option<&str> = None;
     let mut value: Option<&str> = None;
+    let mut unit: Option<&str> = None;
+    let mut within = false;
     
     for pair in pair.into_inner() {
         match pair.as_rule() {
@@ -56,7 +60,14 @@ fn parse_condition(pair: Pair<Rule>) -> ParseResult<NodeFilter>
                 };
             },
             Rule::key => {
-                nf.key = KeyFilter::Equals(pair.as_str().into());
+                // `address/` selects all nested nodes of the group
+                nf.key = match pair.as_str() {
+                    key if key.ends_with('/') => KeyFilter::StartsWith(key.into()),
+                    key => KeyFilter::Equals(key.into())
+                };
+            },
+            Rule::attr_key => {
+                nf.attr = Some(pair.as_str().into());
             },
             Rule::index_single => {
                 for pair in pair.into_inner() {
@@ -98,15 +109,18 @@ fn parse_condition(pair: Pair<Rule>) -> ParseResult<NodeFilter>
             // TODO: merge operator/value into one expression
             // and create it directly in the rule
             Rule::operator => operator = Some(pair.as_str()),
-            Rule::value => {
+            Rule::value | Rule::text_value => {
                 for pair in pair.into_inner() {
                     match pair.as_rule() {
                         Rule::inner_value | Rule::unquoted_value =>
                             value = Some(pair.as_str()),
+                        Rule::unquoted_text => value = Some(pair.as_str().trim()),
                         _ => {},                           
                     }
                 }
             },
+            Rule::unit => unit = Some(pair.as_str()),
+            Rule::within => within = true,
             _ => { warn!("unhandled mql rule '{:?}'", pair.as_rule())}
         }
     }
@@ -115,35 +129,76 @@ fn parse_condition(pair: Pair<Rule>) -> ParseResult<NodeFilter>
 
     debug!("operator = {:?}", operator);
     debug!("value = {:?}", value);
+    debug!("unit = {:?}", unit);
+
     let value_filter = match (operator, value) {
         (Some("="), Some(s)) => ValueFilter::Equals(s.into()),
         (Some("~"), Some(s)) => ValueFilter::Contains(s.into()),
         (Some(">"), Some(s)) => {
-            match s.parse::<f32>() {
-                Ok(value_f32) => ValueFilter::MoreThan(value_f32),
-                _ => ValueFilter::Any
+            match s.parse::<f64>() {
+                Ok(value_f64) => ValueFilter::MoreThan(value_f64),
+                _ => return Err("invalid number")
             }
         },
         (Some("<"), Some(s)) => {
-            match s.parse::<f32>() {
-                Ok(value_f32) => ValueFilter::LessThan(value_f32),
-                _ => ValueFilter::Any
+            match s.parse::<f64>() {
+                Ok(value_f64) => ValueFilter::LessThan(value_f64),
+                _ => return Err("invalid number")
             }
         },
         (Some(">="), Some(s)) => {
-            match s.parse::<f32>() {
-                Ok(value_f32) => ValueFilter::AtLeast(value_f32),
-                _ => ValueFilter::Any
+            match s.parse::<f64>() {
+                Ok(value_f64) => ValueFilter::AtLeast(value_f64),
+                _ => return Err("invalid number")
             }
         },
         (Some("<="), Some(s)) => {
-            match s.parse::<f32>() {
-                Ok(value_f32) => ValueFilter::AtMost(value_f32),
-                _ => ValueFilter::Any
+            match s.parse::<f64>() {
+                Ok(value_f64) => ValueFilter::AtMost(value_f64),
+                _ => return Err("invalid number")
+            }
+        },
+        (Some("<<"), Some(s)) => {
+            match s.parse::<Date>() {
+                Ok(date) => ValueFilter::Before(date),
+                _ => return Err("invalid date")
+            }
+        },
+        (Some(">>"), Some(s)) => {
+            match s.parse::<Date>() {
+                Ok(date) => ValueFilter::After(date),
+                _ => return Err("invalid date")
+            }
+        },
+        (Some("=="), Some(s)) => {
+            match s.parse::<Date>() {
+                Ok(date) => ValueFilter::SameDate(date),
+                _ => return Err("invalid date")
             }
         },
         _ => ValueFilter::Any
     };
+
+    // numeric comparisons with a unit convert the node value into
+    // the given unit before comparing, e.g. `density>5000 kg/m³`
+    let value_filter = match (&value_filter, unit) {
+        (ValueFilter::LessThan(_), Some(unit)) |
+        (ValueFilter::MoreThan(_), Some(unit)) |
+        (ValueFilter::AtLeast(_), Some(unit)) |
+        (ValueFilter::AtMost(_), Some(unit)) => {
+            match Unit::parse(unit) {
+                Some(unit) => ValueFilter::InUnit(Box::new(value_filter), unit),
+                None => return Err("unknown unit")
+            }
+        },
+        _ => value_filter
+    };
+
+    // numeric comparisons with `*` must match all values of a range
+    let value_filter = match within && value_filter.is_numeric() {
+        true => ValueFilter::Within(Box::new(value_filter)),
+        false => value_filter
+    };
     debug!("value-filter = {:?}", value_filter);
 
     nf.value = value_filter;
@@ -157,6 +212,7 @@ fn parse_condition(pair: Pair<Rule>) -> ParseResult<NodeFilter>
 #[cfg(test)]
 mod tests {
     use crate::mql::*;
+    use crate::Memo;
 
     // Try to match all given input item `ok` with the given `rule`.
     // Return a vector of all input strings, that could not be matched
@@ -250,6 +306,127 @@ mod tests {
         assert_eq!(check_ok_err(rule, &ok, &err), (None, None));
     }
 
+    #[test]
+    fn parse_operator() {
+        let rule = Rule::operator;
+        let ok = ["=", "~", "<", "<=", ">", ">=", "<<", ">>", "=="];
+        let err = ["!", "+", "a"];
+        assert_eq!(check_ok_err(rule, &ok, &err), (None, None));
+    }
+
+    #[test]
+    fn parse_date_condition() {
+        let date = |s: &str| s.parse::<Date>().unwrap();
+        let value_filter = |mql: &str| super::parse_mql(mql).unwrap().node_filters.remove(0).value;
+
+        assert_eq!(value_filter("birthday>>2021-11-01"), ValueFilter::After(date("2021-11-01")));
+        assert_eq!(value_filter("birthday<<1808"), ValueFilter::Before(date("1808")));
+        assert_eq!(value_filter("birthday==1808-06"), ValueFilter::SameDate(date("1808-06")));
+        assert_eq!(value_filter("birthday=1808-06"), ValueFilter::Equals("1808-06".into()));
+        assert_eq!(super::parse_mql("birthday<<yesterday").unwrap_err(), "invalid date");
+        assert_eq!(super::parse_mql("birthday==1808-13").unwrap_err(), "invalid date");
+        assert_eq!(super::parse_mql("amu>heavy").unwrap_err(), "invalid number");
+    }
+
+    #[test]
+    fn parse_unit_condition() {
+        let value_filter = |mql: &str| super::parse_mql(mql).unwrap().node_filters.remove(0).value;
+
+        assert_eq!(value_filter("density>5000 kg/m³"),
+                   ValueFilter::InUnit(Box::new(ValueFilter::MoreThan(5000.0)),
+                                       Unit::parse("kg/m³").unwrap()));
+        assert_eq!(value_filter("saturation<=2 mT"),
+                   ValueFilter::InUnit(Box::new(ValueFilter::AtMost(2.0)),
+                                       Unit::parse("mT").unwrap()));
+        assert_eq!(value_filter("saturation=2.35 T"), ValueFilter::Equals("2.35 T".into()));
+        assert_eq!(value_filter("density>5000"), ValueFilter::MoreThan(5000.0));
+
+        assert!(super::parse_mql("density>5000 Monkeys").is_err());
+        assert!(super::parse_mql("density>5000 kg/m³, amu>5").is_ok());
+    }
+
+    #[test]
+    fn parse_text_condition() {
+        let value_filter = |mql: &str| super::parse_mql(mql).unwrap().node_filters.remove(0).value;
+
+        assert_eq!(value_filter("title=The Hobbit"), ValueFilter::Equals("The Hobbit".into()));
+        assert_eq!(value_filter("title=The Lord of the Rings"),
+                   ValueFilter::Equals("The Lord of the Rings".into()));
+        assert_eq!(value_filter("title~Lord of"), ValueFilter::Contains("Lord of".into()));
+        assert_eq!(value_filter("title='The Hobbit'"), ValueFilter::Equals("The Hobbit".into()));
+
+        let filter = super::parse_mql("title=The Lord of the Rings, author~Tolkien").unwrap();
+        assert_eq!(filter.node_filters.len(), 2);
+        assert_eq!(filter.node_filters[0].value, ValueFilter::Equals("The Lord of the Rings".into()));
+
+        // only numeric comparisons accept a unit
+        assert!(super::parse_mql("birthday<<2021 days").is_err());
+        assert!(super::parse_mql("density>5000 kg/m³ extra").is_err());
+    }
+
+    #[test]
+    fn parse_within_condition() {
+        let value_filter = |mql: &str| super::parse_mql(mql).unwrap().node_filters.remove(0).value;
+
+        assert_eq!(value_filter("density*>2"),
+                   ValueFilter::Within(Box::new(ValueFilter::MoreThan(2.0))));
+        assert_eq!(value_filter("density*<=2000 kg/m³"),
+                   ValueFilter::Within(Box::new(
+                       ValueFilter::InUnit(Box::new(ValueFilter::AtMost(2000.0)),
+                                           Unit::parse("kg/m³").unwrap()))));
+        assert_eq!(value_filter("density>2"), ValueFilter::MoreThan(2.0));
+        assert_eq!(value_filter("name*=C"), ValueFilter::Equals("C".into()));
+    }
+
+    #[test]
+    fn parse_attr_condition() {
+        let node_filter = |mql: &str| super::parse_mql(mql).unwrap().node_filters.remove(0);
+
+        let nf = node_filter("@+id=h");
+        assert_eq!(nf.node_type, NodeType::Header);
+        assert_eq!(nf.key, KeyFilter::Any);
+        assert_eq!(nf.attr, Some("id".into()));
+        assert_eq!(nf.value, ValueFilter::Equals("h".into()));
+
+        let nf = node_filter("@element+source");
+        assert_eq!(nf.key, KeyFilter::Equals("element".into()));
+        assert_eq!(nf.attr, Some("source".into()));
+        assert_eq!(nf.value, ValueFilter::Any);
+
+        let nf = node_filter("density[0]+unit=g/cm³");
+        assert_eq!(nf.index, IndexFilter::Single(0));
+        assert_eq!(nf.attr, Some("unit".into()));
+        assert_eq!(nf.value, ValueFilter::Equals("g/cm³".into()));
+
+        let nf = node_filter("+source~Wiki");
+        assert_eq!(nf.node_type, NodeType::Any);
+        assert_eq!(nf.attr, Some("source".into()));
+
+        assert_eq!(node_filter("density>2").attr, None);
+        assert!(super::parse_mql("@+").is_err());
+    }
+
+    #[test]
+    fn parse_path_condition() {
+        let node_filter = |mql: &str| super::parse_mql(mql).unwrap().node_filters.remove(0);
+
+        let nf = node_filter("address/city=Hobbiton");
+        assert_eq!(nf.key, KeyFilter::Equals("address/city".into()));
+        assert_eq!(nf.value, ValueFilter::Equals("Hobbiton".into()));
+
+        let nf = node_filter(".address/~Bag");
+        assert_eq!(nf.node_type, NodeType::Data);
+        assert_eq!(nf.key, KeyFilter::StartsWith("address/".into()));
+        assert_eq!(nf.value, ValueFilter::Contains("Bag".into()));
+
+        let memo = Memo::new("contact", "Frodo")
+            .with(("address/street", "Bagshot Row"))
+            .with(("address/phone/home", "123"));
+        assert!(super::parse_mql("address/~Bag").unwrap().check(&memo));
+        assert!(super::parse_mql("address/=123").unwrap().check(&memo));
+        assert!(!super::parse_mql("address=123").unwrap().check(&memo));
+    }
+
     #[test]
     fn parse_index_single() {
         let rule = Rule::index_single;
@@ -1,18 +1,45 @@
 //! A Node represents a single piece of information, a Key-Value pair.
 //!
-//! A Node consists of key, a value and a map of attributes.
+//! A Node consists of key, a value and a list of attributes.
+//! Attributes keep the order in which they were added, and the same
+//! attribute key may occur more than once, e.g.
+//!
+//! ```text
+//! .discovery Cavendish
+//! +source Wikipedia
+//! +source Britannica
+//! ```
 //!
 
-use std::collections::HashMap;
+use std::convert::TryFrom;
 use crate::value::{Key, Value};
+use crate::unit::Unit;
+use crate::location::Span;
 
 
-/// A Node consists of key, a value and a map of attributes.
-#[derive(Debug, PartialEq)]
+/// A Node consists of key, a value and a list of attributes.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Node {
     pub key: Key,
     pub value: Value,
-    pub attrs: HashMap<Key, Value>
+    pub attrs: Vec<(Key, Value)>,
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Vec::is_empty"))]
+    pub comments: Vec<String>, // comment lines before the node, without `#`
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub location: Option<Span>, // where the node was read from
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "std::ops::Not::not"))]
+    pub inherited: bool, // node was inherited from a prototype
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Vec::is_empty"))]
+    pub inherited_attrs: Vec<Key> // keys of attributes inherited from a prototype
+}
+
+// Comments, location and inheritance are not part of the content of
+// a Node.
+impl PartialEq for Node {
+    fn eq(&self, other: &Self) -> bool {
+        self.key == other.key && self.value == other.value && self.attrs == other.attrs
+    }
 }
 
 impl Node {
@@ -22,27 +49,180 @@ impl Node {
         Node {
             key: key.into(),
             value: value.into(),
-            attrs: HashMap::new()
+            attrs: Vec::new(),
+            comments: Vec::new(),
+            location: None,
+            inherited: false,
+            inherited_attrs: Vec::new()
         }
     }
 
+    /// Returns true if the node was inherited from a prototype, see
+    /// `prototype.rs`.
+    pub fn is_inherited(&self) -> bool {
+        self.inherited
+    }
+
+    /// Returns true if the attributes with the given key were
+    /// inherited from a prototype.
+    pub fn is_inherited_attr<K: Into<Key>>(&self, key: K) -> bool {
+        self.inherited || self.inherited_attrs.contains(&key.into())
+    }
+
+    /// Returns the group of a nested node, i.e. its path key without
+    /// the last part, e.g. `address` for the key `address/city`.
+    pub fn group(&self) -> Option<&str> {
+        self.key.rsplit_once('/').map(|(group, _)| group)
+    }
+
+    /// Returns the name of the node within its group, e.g. `city`
+    /// for the key `address/city`, or the whole key if the node is
+    /// not nested.
+    pub fn name(&self) -> &str {
+        self.key.rsplit('/').next().unwrap_or(&self.key)
+    }
+
+    /// Adds a comment line and returns the instance of the Node.
+    /// The comment is given without the leading `#`.
+    pub fn with_comment<S: Into<String>>(mut self, comment: S) -> Self
+    {
+        self.comments.push(comment.into());
+        self
+    }
+
+    /// Returns the comment lines written before the node, without
+    /// the leading `#`.
+    pub fn comments(&self) -> &[String]
+    {
+        &self.comments
+    }
+
+    pub fn with_location(mut self, location: Span) -> Self
+    {
+        self.location = Some(location);
+        self
+    }
+
+    /// Adds the given attribute and returns the instance of the Node.
+    /// An existing attribute with the same key is kept.
     pub fn with_attr<K, V>(mut self, key: K, value: V) -> Self
     where K: Into<Key>, V: Into<Value>
     {
-        self.attrs.insert(key.into(), value.into());
+        self.push_attr(key, value);
         self
     }
 
-    pub fn with_attrs(mut self, attrs: HashMap<Key, Value>) -> Self
+    pub fn with_attrs(mut self, attrs: Vec<(Key, Value)>) -> Self
     {
         self.attrs = attrs;
         self
     }
 
-    pub fn attrs<'a>(&'a self) -> &'a HashMap<Key, Value>
+    /// Adds the given attribute after all existing attributes.
+    pub fn push_attr<K, V>(&mut self, key: K, value: V)
+    where K: Into<Key>, V: Into<Value>
+    {
+        self.attrs.push((key.into(), value.into()));
+    }
+
+    /// Sets the attribute with the given key to the given value and
+    /// returns the previous value.  The first attribute with the key
+    /// keeps its position, any further attributes with the key are
+    /// removed.  If there is no such attribute, it is added.
+    pub fn set_attr<K, V>(&mut self, key: K, value: V) -> Option<Value>
+    where K: Into<Key>, V: Into<Value>
+    {
+        let key = key.into();
+        match self.attrs.iter().position(|(k, _)| *k == key) {
+            Some(index) => {
+                let previous = std::mem::replace(&mut self.attrs[index].1, value.into());
+                let mut first = true;
+                self.attrs.retain(|(k, _)| *k != key || std::mem::take(&mut first));
+                Some(previous)
+            },
+            None => {
+                self.attrs.push((key, value.into()));
+                None
+            }
+        }
+    }
+
+    /// Removes all attributes with the given key and returns their
+    /// values.
+    pub fn remove_attr<K: Into<Key>>(&mut self, key: K) -> Vec<Value> {
+        let key = key.into();
+        let (removed, kept) = std::mem::take(&mut self.attrs).into_iter()
+            .partition(|(k, _)| *k == key);
+        self.attrs = kept;
+        removed.into_iter().map(|(_, value)| value).collect()
+    }
+
+    /// Renames all attributes with key `from` to `to` and returns the
+    /// number of renamed attributes.
+    pub fn rename_attr<K1, K2>(&mut self, from: K1, to: K2) -> usize
+    where K1: Into<Key>, K2: Into<Key>
+    {
+        let (from, to) = (from.into(), to.into());
+        let mut count = 0;
+        for (key, _) in self.attrs.iter_mut().filter(|(k, _)| *k == from) {
+            *key = to.clone();
+            count += 1;
+        }
+        count
+    }
+
+    /// Returns all attributes in the order they were added.
+    pub fn attrs(&self) -> &[(Key, Value)]
     {
         &self.attrs
     }
+
+    /// Returns the value of the first attribute with the given key.
+    pub fn get_attr<K: Into<Key>>(&self, key: K) -> Option<&Value> {
+        let key = key.into();
+        self.attrs.iter()
+            .find(|(k, _)| *k == key)
+            .map(|(_, value)| value)
+    }
+
+    /// Returns the values of all attributes with the given key.
+    pub fn get_attr_vec<K: Into<Key>>(&self, key: K) -> Vec<&Value> {
+        let key = key.into();
+        self.attrs.iter()
+            .filter(|(k, _)| *k == key)
+            .map(|(_, value)| value)
+            .collect::<Vec<&Value>>()
+    }
+
+    /// Returns true if the Node has at least one attribute with the
+    /// given key.
+    pub fn contains_attr<K: Into<Key>>(&self, key: K) -> bool {
+        let key = key.into();
+        self.attrs.iter().any(|(k, _)| *k == key)
+    }
+
+    /// Returns number and unit of the node value.  The unit is
+    /// either part of the value (`.saturation 2.35 T`) or given by
+    /// the attribute `+unit`:
+    ///
+    /// ```text
+    /// .density 7.87
+    /// +unit g/cm³
+    /// ```
+    pub fn quantity(&self) -> Option<(f64, Unit)> {
+        self.quantity_of(&self.value)
+    }
+
+    /// Returns number and unit of the given value, which is usually
+    /// the node value or one of the bounds of a Value::Range.  If the
+    /// value has no unit, the attribute `+unit` is used.
+    pub fn quantity_of(&self, value: &Value) -> Option<(f64, Unit)> {
+        if value.is_quantity() {
+            return value.quantity();
+        }
+        let unit = Unit::parse(&self.get_attr("unit")?.to_string())?;
+        Some((f64::try_from(value).ok()?, unit))
+    }
 }
 
 impl <K, V> From<(K, V)> for Node
@@ -54,3 +234,75 @@ where K: Into<Key>,
     }
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quantity() {
+        let node = Node::new("saturation", Value::infer("2.35 T"));
+        assert_eq!(node.quantity(), Some((2.35, Unit::parse("T").unwrap())));
+
+        let node = Node::new("density", 7.87).with_attr("unit", "g/cm³");
+        assert_eq!(node.quantity(), Some((7.87, Unit::parse("g/cm³").unwrap())));
+
+        let node = Node::new("density", 7.87);
+        assert_eq!(node.quantity(), None);
+
+        let node = Node::new("density", "high").with_attr("unit", "g/cm³");
+        assert_eq!(node.quantity(), None);
+    }
+
+    #[test]
+    fn attrs() {
+        let node = Node::new("discovery", "Cavendish")
+            .with_attr("source", "Wikipedia")
+            .with_attr("date", Value::infer("1766"))
+            .with_attr("source", "Britannica");
+
+        let keys: Vec<&str> = node.attrs().iter().map(|(key, _)| key.as_str()).collect();
+        assert_eq!(keys, vec!("source", "date", "source"));
+        assert_eq!(node.get_attr("source"), Some(&Value::from("Wikipedia")));
+        assert_eq!(node.get_attr_vec("source"),
+                   vec!(&Value::from("Wikipedia"), &Value::from("Britannica")));
+        assert!(node.contains_attr("date"));
+        assert_eq!(node.get_attr("unit"), None);
+        assert!(node.get_attr_vec("unit").is_empty());
+    }
+
+    #[test]
+    fn path_keys() {
+        let node = Node::new("address/phone/home", "123");
+        assert_eq!(node.group(), Some("address/phone"));
+        assert_eq!(node.name(), "home");
+        let node = Node::new("city", "Hobbiton");
+        assert_eq!(node.group(), None);
+        assert_eq!(node.name(), "city");
+    }
+
+    #[test]
+    fn edit_attrs() {
+        let mut node = Node::new("discovery", "Cavendish")
+            .with_attr("source", "Wikipedia")
+            .with_attr("date", Value::infer("1766"))
+            .with_attr("source", "Britannica");
+
+        assert_eq!(node.set_attr("source", "Nature"), Some(Value::from("Wikipedia")));
+        assert_eq!(node.attrs(), [("source".into(), Value::from("Nature")),
+                                  ("date".into(), Value::infer("1766"))]);
+        assert_eq!(node.set_attr("unit", "g"), None);
+        assert_eq!(node.get_attr("unit"), Some(&Value::from("g")));
+
+        assert_eq!(node.rename_attr("date", "year"), 1);
+        assert_eq!(node.get_attr("year"), Some(&Value::infer("1766")));
+        assert_eq!(node.rename_attr("date", "year"), 0);
+
+        node.push_attr("source", "Britannica");
+        assert_eq!(node.remove_attr("source"),
+                   vec!(Value::from("Nature"), Value::from("Britannica")));
+        assert!(!node.contains_attr("source"));
+        assert!(node.remove_attr("source").is_empty());
+        assert_eq!(node.attrs().len(), 2);
+    }
+}
@@ -4,6 +4,7 @@
 //!
 
 use pest::Parser;
+use pest::error::LineColLocation;
 use pest::iterators::Pair;
 
 #[derive(Parser)]
@@ -11,10 +12,13 @@ use pest::iterators::Pair;
 pub struct MemoParser;
 
 use std::fs;
-use std::collections::HashMap;
 use log::*;
 
 use crate::{Memo, Node, Value, Key};
+use crate::reference::{dangling_refs, demote_refs, duplicate_ids};
+use crate::prototype::apply_prototypes;
+use crate::directory::find_files;
+use crate::location::Span;
 use std::path::{Path, PathBuf};
 
 // TODO:
@@ -39,27 +43,108 @@ where F1: Into<PathBuf>,
 }
 
 pub fn read_from_file(filename: &'_ str) -> Result<Vec<Memo>, ()>
+{
+    let mut memos = read_memos_from_file(filename)
+        .map_err(|msg| error!("{}", msg))?;
+    resolve_memos(&mut memos);
+    Ok(memos)
+}
+
+/// Reads the memos from the given `.mr` file or, if `path` is a
+/// directory, from all `.mr` files below it, see `directory`.  Each
+/// memo records the file it was read from, see `Memo::path`.
+///
+/// `@mr:include` is followed as in `read_from_file`.  A file of the
+/// directory that is included by another file of the directory is
+/// only read as part of the including file.
+pub fn read_from_path(path: &'_ str) -> Result<Vec<Memo>, ()>
+{
+    let mut memos: Vec<Memo> = read_memos_from_path(path)
+        .map_err(|msg| error!("{}", msg))?
+        .into_iter()
+        .flatten()
+        .collect();
+    resolve_memos(&mut memos);
+    Ok(memos)
+}
+
+// Merges prototypes and reports references to unknown memos as well
+// as memos with the same id, once all files are read.  Values that
+// look like references to unknown collections are kept as text.
+pub(crate) fn resolve_memos(memos: &mut [Memo])
+{
+    demote_refs(memos);
+    apply_prototypes(memos);
+    for dangling in dangling_refs(memos) {
+        warn!("{}", dangling);
+    }
+    for duplicate in duplicate_ids(memos) {
+        warn!("{}", duplicate);
+    }
+}
+
+// Reads the memos from the given file including the `@mr:include`
+// files, but without applying prototypes.  This allows to combine
+// the memos of several files before, see `Database::read_from_files`.
+// Fails with a message naming the file and, for syntax errors, the
+// position, e.g. `notes.mr:3:1`.
+pub(crate) fn read_memos_from_file(filename: &'_ str) -> Result<Vec<Memo>, String>
 {
     read_from_file_internal(filename, &mut vec!())
 }
 
+// Like `read_memos_from_file`, but `path` may also be a directory.
+// Returns the memos of each file read, in the order of the files.
+// Fails if any of the files cannot be read.
+pub(crate) fn read_memos_from_path(path: &'_ str) -> Result<Vec<Vec<Memo>>, String>
+{
+    if !Path::new(path).is_dir() {
+        return Ok(vec!(read_memos_from_file(path)?));
+    }
+
+    let files = find_files(path)
+        .map_err(|err| format!("cannot read directory '{}': {}", path, err))?;
+    let mut result = Vec::new();
+    for file in files {
+        let filename = file.to_str()
+            .ok_or_else(|| format!("cannot read '{}': path is not valid UTF-8", file.display()))?;
+        let memos = read_memos_from_file(filename)?;
+        result.push((file, memos));
+    }
+
+    // skip files that have already been included by another file
+    let canonical = |path: &Path| fs::canonicalize(path).ok();
+    let included: Vec<PathBuf> = result.iter()
+        .flat_map(|(file, memos)| memos.iter()
+                  .filter_map(|memo| memo.path())
+                  .filter(move |&path| path != file.as_path()))
+        .filter_map(canonical)
+        .collect();
+    Ok(result.into_iter()
+       .filter(|(file, _)| match canonical(file) {
+           Some(file) => !included.contains(&file),
+           None => true
+       })
+       .map(|(_, memos)| memos)
+       .collect())
+}
+
 fn read_from_file_internal(filename: &'_ str, include_path_trail: &mut Vec<PathBuf>)
-                               -> Result<Vec<Memo>, ()>
+                               -> Result<Vec<Memo>, String>
 {
     // TODO: include files is not working, as we read the whole thing
     // scan for @mr:include and read from all these files
     
     debug!("reading file {}", filename);
     let unparsed_file = fs::read_to_string(filename)
-        .expect("cannot read mr file");
+        .map_err(|err| format!("cannot read '{}': {}", filename, err))?;
 
     include_path_trail.push(Path::new(filename).to_path_buf());
 
-    let result = MemoParser::parse(Rule::file_new, &unparsed_file)
-        .expect("unsuccessful parse")
-        .next().unwrap();
-
-    let mut memos = rule_memos(result.into_inner().next().unwrap())?;
+    let mut memos = parse_file(&unparsed_file, filename)?;
+    for memo in memos.iter_mut() {
+        memo.set_path(filename);
+    }
 
     // Include memos from other files if `@mr:include filename` has
     // been provided.
@@ -73,42 +158,73 @@ fn read_from_file_internal(filename: &'_ str, include_path_trail: &mut Vec<PathB
     {
         let include_path = absolute_include_path(&value, &filename);
         debug!("include path is '{:#?}'", include_path.to_str());
+        let include_file = include_path.to_str()
+            .ok_or_else(|| format!("{}: include path '{}' is not valid UTF-8", filename, include_path.display()))?;
 
         // we could have an include path trail and check if the include
         // is in there. OR even simpler, we could just allow one include :-)
-        debug!("trying to include {}", include_path.to_str().unwrap());
+        debug!("trying to include {}", include_file);
         if include_path_trail.len() < 2 {
-            let included_memos = read_from_file_internal(
-                include_path.to_str().unwrap(), include_path_trail
-            ).unwrap();
-            info!("included {} memos from included file '{}'", included_memos.len(), include_path.to_str().unwrap());
+            let included_memos = read_from_file_internal(include_file, include_path_trail)?;
+            info!("included {} memos from included file '{}'", included_memos.len(), include_file);
             // TODO: include memos at given position
             memos.extend(included_memos);
         } else {
             eprintln!("merula currently does not supporting nested includes");
         }
     }
-    
+
     Ok(memos)        
 }
 
 
+// Reads memos from the text of the given file like `read_from_str`,
+// but reports the position of a syntax error, e.g. `notes.mr:3:1`.
+fn parse_file(input: &str, filename: &str) -> Result<Vec<Memo>, String> {
+    let result = MemoParser::parse(Rule::file_new, input)
+        .map_err(|err| {
+            let (line, column) = match err.line_col {
+                LineColLocation::Pos(pos) => pos,
+                LineColLocation::Span(start, _) => start
+            };
+            format!("{}:{}:{}: cannot parse memo", filename, line, column)
+        })?
+        .next().unwrap();
+    rule_memos(result.into_inner().next().unwrap())
+        .map_err(|_| format!("{}: cannot parse memos", filename))
+}
+
+/// Reads memos from the given `.mr` text.  In contrast to
+/// `read_from_file`, `@mr:include` memos are not followed,
+/// prototypes are not applied, see `prototype::apply_prototypes`,
+/// and references are not checked, see `reference::demote_refs`.
+pub fn read_from_str(input: &str) -> Result<Vec<Memo>, ()> {
+    let result = MemoParser::parse(Rule::file_new, input)
+        .map_err(|_| ())?
+        .next().unwrap();
+    rule_memos(result.into_inner().next().unwrap())
+}
+
 
 pub fn rule_header_node_ml(pair: Pair<Rule>) -> Result<Node, ()> {
-    // header_node_ml = { "@" ~ key ~ value_ml }
+    // header_node_ml = { "@" ~ key ~ value_attrs ~ (NEWLINE ~ attr)* }
+    let span = Span::from_pair(&pair);
     let mut inner = pair.into_inner();
     let key = inner.next().unwrap().as_str();
     let value = inner.next().unwrap().as_str().trim();
-    Ok(Node::new(key, value))
+    Ok(Node::new(key, value).with_attrs(rule_attrs(inner)).with_location(span))
 }
 
 pub fn rule_header_node_eof(pair: Pair<Rule>) -> Result<Node, ()> {
-    // header_node_eof = { "@" ~ key ~ "<<" ~ PUSH(eof) ~ NEWLINE ~ value_eof ~ POP }
+    // header_node_eof = { "@" ~ key ~ "<<" ~ PUSH(eof) ~ inline_attr* ~ NEWLINE ~ value_eof ~ POP ~ (NEWLINE ~ attr)* }
+    let span = Span::from_pair(&pair);
     let mut inner = pair.into_inner();
     let key = inner.next().unwrap().as_str();
-    let _eof = inner.next().unwrap().as_str();
-    let value_eof = inner.next().unwrap().as_str().trim();
-    Ok(Node::new(key, value_eof))
+    let eof = inner.next().unwrap().as_str();
+    let (value_eof, attrs) = rule_value_eof(inner);
+    Ok(Node::new(key, eof_value(value_eof, eof, true))
+       .with_attrs(attrs)
+       .with_location(span))
 }
 
 pub fn rule_header_node(pair: Pair<Rule>) -> Result<Node, ()> {
@@ -121,8 +237,85 @@ pub fn rule_header_node(pair: Pair<Rule>) -> Result<Node, ()> {
     }
 }
 
+// Remove the attribute `+type text` from the given attributes and
+// return true if it was present.  The attribute is a directive for
+// the parser to keep the node value as literal text, e.g. to store
+// the number `42` as a string.  It is not stored in the Node.
+fn take_type_text(attrs: &mut Vec<(Key, Value)>) -> bool {
+    let is_type_text = |(key, value): &(Key, Value)|
+        key == "type" && matches!(value, Value::Text(t) if t == "text");
+    match attrs.iter().position(is_type_text) {
+        Some(index) => {
+            attrs.remove(index);
+            true
+        },
+        None => false
+    }
+}
+
+// Return the Value for the given node value as read from the file.
+fn node_value(value: &str, literal: bool) -> Value {
+    match literal {
+        true => Value::from(value),
+        false => Value::infer(value)
+    }
+}
+
+// Return the Value for the given node value in explicit multi-line
+// form, keeping the separator `eof` of multi-line text.
+fn eof_value(value: &str, eof: &str, literal: bool) -> Value {
+    match value.contains('\n') {
+        true => Value::MultiLineText(value.into(), eof.into()),
+        false => node_value(value, literal)
+    }
+}
+
+// Remove the quotes and escapes from a quoted attribute value.
+fn unquote(s: &str) -> String {
+    let mut result = String::new();
+    let mut chars = s[1..s.len() - 1].chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => result.extend(chars.next()),
+            _ => result.push(c)
+        }
+    }
+    result
+}
+
+// Return key and value of an attribute, given either on a separate
+// line (`+unit T`) or inline (`{+unit T}`).  Quoted inline values are
+// always text.
+fn rule_attr(pair: Pair<Rule>) -> (Key, Value) {
+    // attr = { "+" ~ key ~ value }
+    // inline_attr = { "{" ~ "+" ~ key ~ (quoted_value | bare_value) ~ "}" }
+    let mut inner = pair.into_inner();
+    let key = inner.next().unwrap().as_str();
+    let value = inner.next().unwrap();
+    let value = match value.as_rule() {
+        Rule::quoted_value => Value::from(unquote(value.as_str())),
+        Rule::bare_value => Value::infer(value.as_str().trim()),
+        _ => Value::infer(value.as_str())
+    };
+    (key.into(), value)
+}
+
+// Collect the attributes of a data node, in file order.
+fn rule_attrs<'a>(pairs: impl Iterator<Item=Pair<'a, Rule>>) -> Vec<(Key, Value)> {
+    pairs.map(rule_attr).collect()
+}
+
+// Split the remaining pairs of an explicit multi-line node into the
+// value and the attributes, given inline after the separator or on
+// separate lines after the value.
+fn rule_value_eof<'a>(pairs: impl Iterator<Item=Pair<'a, Rule>>) -> (&'a str, Vec<(Key, Value)>) {
+    let (values, attrs): (Vec<_>, Vec<_>) = pairs.partition(|pair| pair.as_rule() == Rule::value_eof);
+    (values[0].as_str().trim(), rule_attrs(attrs.into_iter()))
+}
+
 pub fn rule_data_multinode_ml(pair: Pair<Rule>) -> Result<Vec<Node>, ()> {
-    // data_multinode_ml = { "." ~ key ~ sep ~ value_ml }
+    // data_multinode_ml = { "." ~ key ~ sep ~ value_attrs ~ (NEWLINE ~ attr)* }
+    let span = Span::from_pair(&pair);
     let mut nodes = Vec::new();
     let mut inner = pair.into_inner();
     let key = inner.next().unwrap().as_str();
@@ -131,20 +324,16 @@ pub fn rule_data_multinode_ml(pair: Pair<Rule>) -> Result<Vec<Node>, ()> {
         x => x
     };
     let values = inner.next().unwrap().as_str();
-    let mut attrs = HashMap::<Key, Value>::new();
-    for attr in inner {
-        let mut attr_inner = attr.into_inner();
-        let attr_key = attr_inner.next().unwrap().as_str();
-        let attr_value = attr_inner.next().unwrap().as_str();
-        attrs.insert(attr_key.into(), attr_value.into());
-    }
+    let mut attrs = rule_attrs(inner);
+    let literal = take_type_text(&mut attrs);
 
     // split value by given separator, each value is trimmed
     for value in values.split(sep) {
         let value = value.trim();
         if value.len() > 0 {
-            let node = Node::new(key, value.trim())
-                .with_attrs(attrs.clone());
+            let node = Node::new(key, node_value(value, literal))
+                .with_attrs(attrs.clone())
+                .with_location(span);
             nodes.push(node);
         }
     }
@@ -153,7 +342,8 @@ pub fn rule_data_multinode_ml(pair: Pair<Rule>) -> Result<Vec<Node>, ()> {
 }
 
 pub fn rule_data_multinode_eof(pair: Pair<Rule>) -> Result<Vec<Node>, ()> {
-    // data_multinode_eof = { "." ~ key ~ sep ~ "<<" ~ PUSH(eof) ~ NEWLINE ~ value_eof ~ POP }
+    // data_multinode_eof = { "." ~ key ~ sep ~ "<<" ~ PUSH(eof) ~ inline_attr* ~ NEWLINE ~ value_eof ~ POP ~ (NEWLINE ~ attr)* }
+    let span = Span::from_pair(&pair);
     let mut nodes = Vec::new();
     let mut inner = pair.into_inner();
     let key = inner.next().unwrap().as_str();
@@ -162,12 +352,16 @@ pub fn rule_data_multinode_eof(pair: Pair<Rule>) -> Result<Vec<Node>, ()> {
         x => x
     };
     let _eof = inner.next().unwrap().as_str();
-    let values = inner.next().unwrap().as_str().trim();
+    let (values, mut attrs) = rule_value_eof(inner);
+    let literal = take_type_text(&mut attrs);
     // split value by given separator, each value is trimmed
     for value in values.split(sep) {
         let value = value.trim();
         if value.len() > 0 {
-            nodes.push(Node::new(key, value.trim()));
+            let node = Node::new(key, node_value(value, literal))
+                .with_attrs(attrs.clone())
+                .with_location(span);
+            nodes.push(node);
         }
     }
     Ok(nodes)
@@ -176,27 +370,27 @@ pub fn rule_data_multinode_eof(pair: Pair<Rule>) -> Result<Vec<Node>, ()> {
 
 
 pub fn rule_data_node_ml(pair: Pair<Rule>) -> Result<Node, ()> {
-    // data_node_ml = { "." ~ key ~ value_ml }
+    // data_node_ml = { "." ~ key ~ value_attrs ~ (NEWLINE ~ attr)* }
+    let span = Span::from_pair(&pair);
     let mut inner = pair.into_inner();
     let key = inner.next().unwrap().as_str();
     let value = inner.next().unwrap().as_str().trim();
-    let mut node = Node::new(key, value);
-    for attr in inner {
-        let mut attr_inner = attr.into_inner();
-        let attr_key = attr_inner.next().unwrap().as_str();
-        let attr_value = attr_inner.next().unwrap().as_str();
-        node.attrs.insert(attr_key.into(), attr_value.into());
-    }
-    Ok(node)
+    let mut attrs = rule_attrs(inner);
+    let literal = take_type_text(&mut attrs);
+    Ok(Node::new(key, node_value(value, literal)).with_attrs(attrs).with_location(span))
 }
 
 pub fn rule_data_node_eof(pair: Pair<Rule>) -> Result<Node, ()> {
-    // data_node_eof = { "." ~ key ~ "<<" ~ PUSH(eof) ~ NEWLINE ~ value_eof ~ POP }
+    // data_node_eof = { "." ~ key ~ "<<" ~ PUSH(eof) ~ inline_attr* ~ NEWLINE ~ value_eof ~ POP ~ (NEWLINE ~ attr)* }
+    let span = Span::from_pair(&pair);
     let mut inner = pair.into_inner();
     let key = inner.next().unwrap().as_str();
-    let _eof = inner.next().unwrap().as_str();
-    let value_eof = inner.next().unwrap().as_str().trim();
-    Ok(Node::new(key, value_eof))
+    let eof = inner.next().unwrap().as_str();
+    let (value_eof, mut attrs) = rule_value_eof(inner);
+    let literal = take_type_text(&mut attrs);
+    Ok(Node::new(key, eof_value(value_eof, eof, literal))
+       .with_attrs(attrs)
+       .with_location(span))
 }
 
 pub fn rule_data_node(pair: Pair<Rule>) -> Result<Node, ()> {
@@ -220,20 +414,55 @@ pub fn rule_data_multinode(pair: Pair<Rule>) -> Result<Vec<Node>, ()> {
     
 }
 
+// Return the text of a comment, without the leading `#`.
+fn rule_comment(pair: Pair<Rule>) -> String {
+    // comment = @{ "#" ~ value_char* }
+    pair.as_str()[1..].to_string()
+}
+
+// Expand a relative key such as `/city` to a path key, using the
+// group of the previous data node, e.g. `address/city` after
+// `address/street`.  If there is no such group, the key is read as
+// top-level key, e.g. `city`, and a warning is given.
+fn resolve_key(node: &mut Node, memo: &Memo) {
+    if let Some(name) = node.key.strip_prefix('/') {
+        let group = memo.data().last().and_then(|previous| previous.group());
+        node.key = match group {
+            Some(group) => format!("{}/{}", group, name),
+            None => {
+                let at = node.location.map(|span| format!(" at {}", span)).unwrap_or_default();
+                warn!("@{} {}: relative key '.{}'{} without group, read as '.{}'",
+                      memo.collection(), memo.title(), node.key, at, name);
+                name.to_string()
+            }
+        };
+    }
+}
+
 pub fn rule_memo(pair: Pair<Rule>) -> Result<Memo, ()> {
-    // memo = { header_node ~ (NEWLINE ~ data_node)* }
+    // memo = { header_node ~ (NEWLINE ~ (comment ~ NEWLINE)* ~ (data_multinode|data_node))* }
     let mut inner = pair.clone().into_inner();
     let p = inner.next().unwrap();
 
     if let Ok(header) = rule_header_node(p) {
         let mut memo = Memo::new(header.key, header.value);
+        memo.header_mut().attrs = header.attrs;
+        memo.header_mut().location = header.location;
+        // comments belong to the following node
+        let mut comments = vec!();
         for data_pair in inner {
             match data_pair.as_rule() {
+                Rule::comment => comments.push(rule_comment(data_pair)),
                 Rule::data_node => {
-                    memo.push(rule_data_node(data_pair).unwrap());
+                    let mut node = rule_data_node(data_pair).unwrap();
+                    node.comments = std::mem::take(&mut comments);
+                    resolve_key(&mut node, &memo);
+                    memo.push(node);
                 }
                 Rule::data_multinode => {
-                    for node in rule_data_multinode(data_pair).unwrap() {
+                    for mut node in rule_data_multinode(data_pair).unwrap() {
+                        node.comments = std::mem::take(&mut comments);
+                        resolve_key(&mut node, &memo);
                         memo.push(node)
                     }
                 },
@@ -249,14 +478,23 @@ pub fn rule_memo(pair: Pair<Rule>) -> Result<Memo, ()> {
 pub fn rule_memos(pair: Pair<Rule>) -> Result<Vec<Memo>, ()> {
     // memos = { (comment | memo | NEWLINE)* }
     let mut memos = Vec::<Memo>::new();
+    // comments belong to the following memo, those at the end of the
+    // file to the last memo
+    let mut comments = vec!();
     for token in pair.into_inner() {
         match token.as_rule() {
+            Rule::comment => comments.push(rule_comment(token)),
             Rule::memo => {
-                memos.push(rule_memo(token)?);
+                let mut memo = rule_memo(token)?;
+                memo.header_mut().comments = std::mem::take(&mut comments);
+                memos.push(memo);
             },
             _ => { } // ignore silently
         }
     }
+    if let Some(memo) = memos.last_mut() {
+        memo.set_trailing_comments(comments);
+    }
     Ok(memos)
 }
 
@@ -279,6 +517,23 @@ mod test_other {
         let result = absolute_include_path(files.0, files.1);
         assert_eq!(PathBuf::from(files.2), result);
     }
+
+    #[test]
+    fn test_read_errors() {
+        assert!(read_from_file("/nonexistent.mr").is_err());
+
+        let dir = std::env::temp_dir().join(format!("merula-errors-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let write = |name: &str, content: &str| {
+            let path = dir.join(name);
+            fs::write(&path, content).unwrap();
+            path.to_str().unwrap().to_string()
+        };
+        assert!(read_from_file(&write("orphan.mr", ".orphan\n@note x\n")).is_err());
+        assert!(read_from_file(&write("include.mr", "@mr:include missing.mr\n")).is_err());
+        assert!(read_from_file(&write("ok.mr", "@note x\n")).is_ok());
+        fs::remove_dir_all(&dir).unwrap();
+    }
 }
 
 
@@ -305,6 +560,13 @@ mod test_parser {
         let result = MemoParser::parse(Rule::data_node_eof, &input);
         let node = rule_data_node_eof(result.unwrap().next().unwrap());
         assert_eq!(node, Ok(Node::new("color", "blue")));        
+
+        let input = ".colors<<END\nblue\nred\nEND\n+tag foo";
+        let result = MemoParser::parse(Rule::data_node_eof, input);
+        let node = rule_data_node_eof(result.unwrap().next().unwrap());
+        let expected = Node::new("colors", Value::MultiLineText("blue\nred".into(), "END".into()))
+            .with_attr("tag", "foo");
+        assert_eq!(node, Ok(expected));
     }
 
     #[test]
@@ -352,6 +614,13 @@ mod test_parser {
         let nodes = rule_data_multinode_eof(result.unwrap().next().unwrap());
         let expected = vec!(Node::new("color", "blue"), Node::new("color", "red"));
         assert_eq!(nodes, Ok(expected));
+
+        let input = ".color,<<EOF\nblue, red\nEOF\n+tag foo";
+        let result = MemoParser::parse(Rule::data_multinode_eof, input);
+        let nodes = rule_data_multinode_eof(result.unwrap().next().unwrap());
+        let expected = vec!(Node::new("color", "blue").with_attr("tag", "foo"),
+                            Node::new("color", "red").with_attr("tag", "foo"));
+        assert_eq!(nodes, Ok(expected));
     }
 
     #[test]
@@ -399,6 +668,25 @@ mod test_parser {
         assert_eq!(node, Ok(Node::new("colors", "blue\nred")));
     }
 
+    #[test]
+    fn test_header_attrs() {
+        let input = "@element H\n+id h\n+source Wikipedia\n.number 1";
+        let result = MemoParser::parse(Rule::memo, input);
+        let memo = rule_memo(result.unwrap().next().unwrap());
+        let expect = Memo::new("element", "H")
+            .with_attr("id", "h")
+            .with_attr("source", "Wikipedia")
+            .with(("number", 1));
+        assert_eq!(memo, Ok(expect));
+
+        let input = "@note<<END\nfirst\nsecond\nEND\n+lang en";
+        let result = MemoParser::parse(Rule::header_node_eof, input);
+        let node = rule_header_node_eof(result.unwrap().next().unwrap());
+        let expect = Node::new("note", Value::MultiLineText("first\nsecond".into(), "END".into()))
+            .with_attr("lang", "en");
+        assert_eq!(node, Ok(expect));
+    }
+
 
     #[test]
     fn test_fn_rule_memo() {
@@ -467,4 +755,183 @@ mod test_parser {
         assert_eq!(memos, Ok(expect));
 
     }
+
+    #[test]
+    fn test_comments() {
+        let input = "# books\n#\n\n@book The Hobbit\n.author Tolkien\n# main characters\n.character, Bilbo, Gandalf\n#+date 1937\n# another book\n@book The Silmarillion";
+        let result = MemoParser::parse(Rule::memos, input);
+        let memos = rule_memos(result.unwrap().next().unwrap()).unwrap();
+        assert_eq!(memos.len(), 2);
+        assert_eq!(memos[0].comments(), [" books", ""]);
+        assert!(memos[0].get("author").unwrap().comments().is_empty());
+        let characters = memos[0].get_vec("character");
+        assert_eq!(characters[0].comments(), [" main characters"]);
+        assert!(characters[1].comments().is_empty());
+        assert_eq!(characters[1].value, Value::from("Gandalf"));
+        assert_eq!(memos[1].comments(), ["+date 1937", " another book"]);
+
+        // a comment ends an implicit multiline value
+        let input = "@book The Hobbit\n.doc first\nsecond\n# comment\n.author Tolkien";
+        let result = MemoParser::parse(Rule::memo, input);
+        let memo = rule_memo(result.unwrap().next().unwrap()).unwrap();
+        assert_eq!(memo.get("doc").unwrap().value, Value::from("first\nsecond"));
+        assert_eq!(memo.get("author").unwrap().comments(), [" comment"]);
+
+        // elsewhere, `#` belongs to an implicit multiline value
+        let input = "@template basic\n.body {doc}\n#\n\n.doc first\n# second\n+lang en\n\n@app merula";
+        let memos = read_from_str(input).unwrap();
+        assert_eq!(memos[0].get("body").unwrap().value, Value::from("{doc}\n#"));
+        assert_eq!(memos[0].get("doc").unwrap().value, Value::from("first\n# second"));
+        assert!(memos[1].comments().is_empty());
+
+        // comments at the end of the file are kept with the last memo
+        let input = "@book The Hobbit\n.doc first\n# second\n\n# end\n#\n\n# of file\n";
+        let memos = read_from_str(input).unwrap();
+        assert_eq!(memos[0].get("doc").unwrap().value, Value::from("first\n# second"));
+        assert_eq!(memos[0].trailing_comments(), [" end", "", " of file"]);
+        let input = "# only comments\n";
+        assert!(read_from_str(input).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_nested_keys() {
+        let input = "@contact Frodo\n.address/street Bagshot Row\n./city Hobbiton\n./phone/home 123\n./mobile 456\n.tag, friend, hobbit\n.address/city Bree\n./tag, inn, pony";
+        let memos = read_from_str(input).unwrap();
+        let keys: Vec<&str> = memos[0].data().map(|node| node.key.as_str()).collect();
+        assert_eq!(keys, vec!("address/street", "address/city", "address/phone/home",
+                              "address/phone/mobile", "tag", "tag", "address/city",
+                              "address/tag", "address/tag"));
+        assert_eq!(memos[0].get("address/city").unwrap().value, Value::from("Hobbiton"));
+
+        // a relative key without group is read as top-level key
+        let result = MemoParser::parse(Rule::memo, "@contact Sam\n./city Hobbiton");
+        let memo = rule_memo(result.unwrap().next().unwrap()).unwrap();
+        assert_eq!(memo.get("city").unwrap().value, Value::from("Hobbiton"));
+        let memos = read_from_str("@contact Sam\n.name Sam\n./tag, hobbit, gardener\n").unwrap();
+        let keys: Vec<&str> = memos[0].data().map(|node| node.key.as_str()).collect();
+        assert_eq!(keys, vec!("name", "tag", "tag"));
+    }
+
+    #[test]
+    fn test_ref_values() {
+        let input = "@book The Lord of the Rings\n.author @author J.R.R. Tolkien\n.character, @character Frodo, @character Sam";
+        let result = MemoParser::parse(Rule::memo, input);
+        let memo = rule_memo(result.unwrap().next().unwrap());
+        let expect = Memo::new("book", "The Lord of the Rings")
+            .with(("author", Value::Ref("author".into(), "J.R.R. Tolkien".into())))
+            .with(("character", Value::Ref("character".into(), "Frodo".into())))
+            .with(("character", Value::Ref("character".into(), "Sam".into())));
+        assert_eq!(memo, Ok(expect));
+    }
+
+    #[test]
+    fn test_typed_values() {
+        let input = "@element Fe\n.number 26\n.amu 55.845\n.ferromagnetic true\n.radioactive no\n.electrons [Ar] 3d^6 4s^2";
+        let result = MemoParser::parse(Rule::memo, input);
+        let memo = rule_memo(result.unwrap().next().unwrap());
+        let expect = Memo::new("element", "Fe")
+            .with(("number", 26))
+            .with(("amu", 55.845))
+            .with(("ferromagnetic", true))
+            .with(("radioactive", false))
+            .with(("electrons", "[Ar] 3d^6 4s^2"));
+        assert_eq!(memo, Ok(expect));
+
+        // `+type text` keeps the value as text and is not stored
+        let input = ".number 26\n+type text\n+source Wikipedia";
+        let result = MemoParser::parse(Rule::data_node_ml, input);
+        let node = rule_data_node_ml(result.unwrap().next().unwrap());
+        let expect = Node::new("number", Value::Text("26".into()))
+            .with_attr("source", "Wikipedia");
+        assert_eq!(node, Ok(expect));
+
+        let input = ".number, 1, 2\n+type text";
+        let result = MemoParser::parse(Rule::data_multinode_ml, input);
+        let nodes = rule_data_multinode_ml(result.unwrap().next().unwrap());
+        let expect = vec!(Node::new("number", "1"), Node::new("number", "2"));
+        assert_eq!(nodes, Ok(expect));
+    }
+
+    #[test]
+    fn test_temporal_values() {
+        let input = "@author J.R.R. Tolkien\n.birthday 1892-01-03\n.discovery Cavendish\n+date 1808-06-30";
+        let result = MemoParser::parse(Rule::memo, input);
+        let memo = rule_memo(result.unwrap().next().unwrap()).unwrap();
+        let date = |s: &str| Value::Date(s.parse().unwrap());
+        assert_eq!(memo.get("birthday").unwrap().value, date("1892-01-03"));
+        assert_eq!(memo.get("discovery").unwrap().get_attr("date"), Some(&date("1808-06-30")));
+    }
+
+    #[test]
+    fn test_inline_attrs() {
+        let input = ".saturation 2.35 {+unit T}";
+        let result = MemoParser::parse(Rule::data_node_ml, input);
+        let node = rule_data_node_ml(result.unwrap().next().unwrap());
+        assert_eq!(node, Ok(Node::new("saturation", 2.35).with_attr("unit", "T")));
+
+        let input = ".density 2.01 {+unit g/cm³} {+state \"liquid, at {room} temperature\"}\n+source Wikipedia";
+        let result = MemoParser::parse(Rule::data_node_ml, input);
+        let node = rule_data_node_ml(result.unwrap().next().unwrap());
+        let expect = Node::new("density", 2.01)
+            .with_attr("unit", "g/cm³")
+            .with_attr("state", "liquid, at {room} temperature")
+            .with_attr("source", "Wikipedia");
+        assert_eq!(node, Ok(expect));
+
+        // quoted values are text, escapes are resolved
+        let input = ".code X {+number \"42\"} {+quote \"say \\\"hi\\\"\"} {+type text}";
+        let result = MemoParser::parse(Rule::data_node_ml, input);
+        let node = rule_data_node_ml(result.unwrap().next().unwrap());
+        let expect = Node::new("code", "X")
+            .with_attr("number", "42")
+            .with_attr("quote", "say \"hi\"");
+        assert_eq!(node, Ok(expect));
+
+        // braces that are not inline attributes belong to the value
+        for input in &[".row <td>{number}</td>", ".set {+a} and more", ".set {+a 1} and more"] {
+            let result = MemoParser::parse(Rule::data_node_ml, input);
+            let node = rule_data_node_ml(result.unwrap().next().unwrap()).unwrap();
+            assert_eq!(format!(".{} {}", node.key, node.value), *input);
+            assert!(node.attrs().is_empty());
+        }
+
+        let input = "@element C\n.density 1.8 - 2.1 {+unit g/cm³}\n.state solid";
+        let result = MemoParser::parse(Rule::memo, input);
+        let memo = rule_memo(result.unwrap().next().unwrap()).unwrap();
+        assert_eq!(memo.get("density").unwrap().get_attr("unit"), Some(&Value::from("g/cm³")));
+        assert_eq!(memo.data_count(), 2);
+
+        // all kinds of nodes, inline attributes come first
+        let input = "@element C {+id c}\n+source Wikipedia\n.tags, solid, black {+lang en}\n\
+                     .doc<<END {+lang en}\nfirst\nsecond\nEND\n+source Wikipedia\n\
+                     .tags,<<END {+lang \"de\"}\nfest, schwarz\nEND";
+        let result = MemoParser::parse(Rule::memo, input);
+        let memo = rule_memo(result.unwrap().next().unwrap()).unwrap();
+        assert_eq!(memo.header().attrs(), [("id".into(), "c".into()), ("source".into(), "Wikipedia".into())]);
+        assert_eq!(memo.title(), "C");
+        let tags = memo.get_vec("tags");
+        assert_eq!(tags.len(), 4);
+        assert!(tags.iter().all(|node| node.get_attr("lang").is_some()));
+        assert_eq!(tags[3].value, Value::from("schwarz"));
+        let doc = memo.get("doc").unwrap();
+        assert_eq!(doc.value, Value::MultiLineText("first\nsecond".into(), "END".into()));
+        assert_eq!(doc.attrs(), [("lang".into(), "en".into()), ("source".into(), "Wikipedia".into())]);
+
+        let input = "@note<<END {+type text}\nfirst\nsecond\nEND";
+        let result = MemoParser::parse(Rule::header_node_eof, input);
+        let node = rule_header_node_eof(result.unwrap().next().unwrap()).unwrap();
+        assert_eq!(node.get_attr("type"), Some(&Value::from("text")));
+    }
+
+    #[test]
+    fn test_repeated_attrs() {
+        let input = ".discovery Cavendish\n+source Wikipedia\n+date 1766\n+source Britannica";
+        let result = MemoParser::parse(Rule::data_node_ml, input);
+        let node = rule_data_node_ml(result.unwrap().next().unwrap());
+        let expect = Node::new("discovery", "Cavendish")
+            .with_attr("source", "Wikipedia")
+            .with_attr("date", 1766)
+            .with_attr("source", "Britannica");
+        assert_eq!(node, Ok(expect));
+    }
 }
@@ -3,3 +3,4 @@ pub use crate::memo::{Memo, NodeType};
 pub use crate::node::{Node};
 pub use crate::value::{Value, Key};
 pub use crate::filter::{NodeFilter, KeyFilter, ValueFilter, MemoFilter};
+pub use crate::database::{Database, Duplicates};
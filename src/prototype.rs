@@ -0,0 +1,199 @@
+//! Prototype memos with default nodes.
+//!
+//! A memo of the collection `mr:prototype` defines nodes that other
+//! memos inherit if their header has the attribute `+extends` with
+//! the title of the prototype:
+//!
+//! ```text
+//! @mr:prototype element
+//! .state solid
+//! .density
+//! +unit g/cm³
+//!
+//! @element Fe
+//! +extends element
+//! .density 7.87
+//! ```
+//!
+//! A memo inherits all nodes of its prototype whose key it does not
+//! define itself, here `.state solid`.  Local nodes take precedence,
+//! but inherit the attributes they lack, so that `.density 7.87`
+//! gets the attribute `+unit g/cm³`.  A prototype node without value
+//! only provides attributes and is never inherited as a node.
+//!
+//! The value of `+extends` may also be a reference such as `@#id`.
+//! Prototypes may extend other prototypes.  If a memo has several
+//! `+extends` attributes, the earlier prototypes take precedence.
+//!
+//! Inherited nodes and attributes are marked, see
+//! `Node::is_inherited` and `Node::is_inherited_attr`, and they are
+//! not written back by the `writer`.
+//!
+
+use log::*;
+
+use crate::{Memo, Node, Key, Value};
+use crate::memo::MemoId;
+use crate::reference::resolve;
+
+/// Collection of prototype memos.
+pub const PROTOTYPE: &str = "mr:prototype";
+
+/// Returns the prototype the given `+extends` value refers to, i.e.
+/// the prototype with the given title or the referenced memo.
+pub fn find_prototype<'a>(memos: &'a [Memo], extends: &Value) -> Option<&'a Memo> {
+    if extends.is_ref() {
+        return resolve(memos, extends);
+    }
+    let title = extends.to_string();
+    memos.iter().find(|memo| memo.collection() == PROTOTYPE && memo.title() == title)
+}
+
+// Merges the `inherited` nodes into the `local` nodes.
+fn merge(local: &mut Vec<Node>, inherited: Vec<Node>) {
+    let local_keys: Vec<Key> = local.iter().map(|node| node.key.clone()).collect();
+    for mut node in inherited {
+        if local_keys.contains(&node.key) {
+            for local_node in local.iter_mut().filter(|n| n.key == node.key) {
+                let missing: Vec<&(Key, Value)> = node.attrs().iter()
+                    .filter(|(key, _)| !local_node.contains_attr(key.as_str()))
+                    .collect();
+                for (key, value) in missing {
+                    local_node.push_attr(key.as_str(), value.clone());
+                    if !local_node.inherited_attrs.contains(key) {
+                        local_node.inherited_attrs.push(key.clone());
+                    }
+                }
+            }
+        } else {
+            node.inherited = true;
+            local.push(node);
+        }
+    }
+}
+
+// Returns the data nodes the given memo inherits from its prototypes,
+// including the nodes the prototypes inherit themselves.  The `trail`
+// holds the ids of the prototypes visited so far to detect cycles.
+fn inherited_nodes(memos: &[Memo], memo: &Memo, trail: &mut Vec<MemoId>) -> Vec<Node> {
+    let mut nodes = Vec::new();
+    for extends in memo.header().get_attr_vec("extends") {
+        let prototype = match find_prototype(memos, extends) {
+            Some(prototype) => prototype,
+            None => {
+                warn!("@{} {}: unknown prototype '{}'", memo.collection(), memo.title(), extends);
+                continue;
+            }
+        };
+        let id = prototype.id();
+        if trail.contains(&id) {
+            warn!("@{} {}: cyclic prototype '{}'", memo.collection(), memo.title(), extends);
+            continue;
+        }
+        trail.push(id);
+        let mut prototype_nodes: Vec<Node> = prototype.data().cloned().collect();
+        merge(&mut prototype_nodes, inherited_nodes(memos, prototype, trail));
+        trail.pop();
+        merge(&mut nodes, prototype_nodes);
+    }
+    nodes
+}
+
+/// Merges the nodes of the prototypes into all memos that have the
+/// header attribute `+extends`.  Unknown and cyclic prototypes are
+/// reported as warnings.
+pub fn apply_prototypes(memos: &mut [Memo]) {
+    for index in 0..memos.len() {
+        if !memos[index].header().contains_attr("extends") {
+            continue;
+        }
+        let mut trail = vec!(memos[index].id());
+        let inherited = inherited_nodes(memos, &memos[index], &mut trail);
+        if inherited.is_empty() {
+            continue;
+        }
+
+        let memo = &mut memos[index];
+        let mut data = memo.replace_data(Vec::new());
+        merge(&mut data, inherited);
+        // nodes without value only provide attributes
+        data.retain(|node| !node.inherited || !node.value.to_string().is_empty());
+        memo.replace_data(data);
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::read_from_str;
+
+    fn sample() -> Vec<Memo> {
+        let input = "@mr:prototype element\n.state solid\n.density\n+unit g/cm³\n\n\
+                     @mr:prototype metal\n+extends element\n.tag metal\n.state solid\n+source Wikipedia\n\n\
+                     @element Fe\n+extends metal\n.density 7.87\n\n\
+                     @element Hg\n+extends metal\n.state liquid\n\n\
+                     @element O\n.state gas";
+        let mut memos = read_from_str(input).unwrap();
+        apply_prototypes(&mut memos);
+        memos
+    }
+
+    #[test]
+    fn inherit_nodes() {
+        let memos = sample();
+        let iron = &memos[2];
+        let keys: Vec<&str> = iron.data().map(|node| node.key.as_str()).collect();
+        assert_eq!(keys, vec!("density", "tag", "state"));
+
+        let density = iron.get("density").unwrap();
+        assert!(!density.is_inherited());
+        assert_eq!(density.get_attr("unit"), Some(&Value::from("g/cm³")));
+        assert!(density.is_inherited_attr("unit"));
+        assert_eq!(density.quantity().unwrap().0, 7.87);
+
+        let state = iron.get("state").unwrap();
+        assert!(state.is_inherited());
+        assert_eq!(state.value, Value::from("solid"));
+        assert_eq!(state.get_attr("source"), Some(&Value::from("Wikipedia")));
+
+        // local nodes take precedence
+        let mercury = &memos[3];
+        assert_eq!(mercury.get_vec("state").len(), 1);
+        assert_eq!(mercury.get("state").unwrap().value, Value::from("liquid"));
+        assert!(mercury.get("state").unwrap().is_inherited_attr("source"));
+        assert!(!mercury.contains_key("density"));
+
+        assert_eq!(memos[4].data_count(), 1);
+    }
+
+    #[test]
+    fn write_without_inherited() {
+        let memos = sample();
+        assert_eq!(memos[3].to_string(), "@element Hg\n+extends metal\n.state liquid\n");
+    }
+
+    #[test]
+    fn extends_by_ref() {
+        let input = "@mr:prototype app\n+id app\n.tag software\n\n\
+                     @app merula\n+extends @#app\n\n\
+                     @app loop\n+extends @app merula\n\n\
+                     @app unknown\n+extends nothing";
+        let mut memos = read_from_str(input).unwrap();
+        apply_prototypes(&mut memos);
+        assert_eq!(memos[1].get("tag").unwrap().value, Value::from("software"));
+        assert!(memos[2].get("tag").unwrap().is_inherited());
+        assert!(memos[3].is_empty());
+    }
+
+    #[test]
+    fn cyclic_prototypes() {
+        let input = "@mr:prototype a\n+extends b\n.x 1\n\n\
+                     @mr:prototype b\n+extends a\n.y 2\n\n\
+                     @item z\n+extends a";
+        let mut memos = read_from_str(input).unwrap();
+        apply_prototypes(&mut memos);
+        let keys: Vec<&str> = memos[2].data().map(|node| node.key.as_str()).collect();
+        assert_eq!(keys, vec!("x", "y"));
+    }
+}
@@ -0,0 +1,262 @@
+//! References between memos.
+//!
+//! A value of the form `@collection title` refers to the memo with
+//! the given collection and title (`Value::Ref`), e.g.
+//!
+//! ```text
+//! @book The Lord of the Rings
+//! .author @author J.R.R. Tolkien
+//! ```
+//!
+//! A value of the form `@#id` refers to the memo with the given id
+//! (`Value::RefById`), which stays valid if the title changes:
+//!
+//! ```text
+//! @author J.R.R. Tolkien
+//! +id tolkien
+//!
+//! @book The Hobbit
+//! .author @#tolkien
+//! ```
+//!
+//! References are resolved against a list of loaded memos.  A
+//! reference without a matching memo is called a dangling reference.
+//! If several memos have the same id, a reference by id resolves to
+//! the first of them, see `duplicate_ids`.
+//!
+//! Any text of the form `@word rest` looks like a reference.  Once a
+//! file is read, such a value is only kept as reference if `word` is
+//! the collection of a loaded memo, otherwise it is turned back into
+//! text, see `demote_refs`.
+//!
+
+use std::collections::{HashMap, HashSet};
+
+use crate::{Memo, Node, Key, Value};
+use crate::memo::MemoId;
+
+/// Returns the memo the given value refers to, or None if the value
+/// is not a reference or if there is no such memo.
+pub fn resolve<'a>(memos: &'a [Memo], value: &Value) -> Option<&'a Memo> {
+    match value {
+        Value::Ref(collection, title) => memos.iter()
+            .find(|memo| &memo.collection() == collection && &memo.title() == title),
+        Value::RefById(id) => find_by_id(memos, id),
+        _ => None
+    }
+}
+
+/// Returns the memo with the given id, see `Memo::id`.
+pub fn find_by_id<'a>(memos: &'a [Memo], id: &str) -> Option<&'a Memo> {
+    memos.iter().find(|memo| memo.id() == id)
+}
+
+/// A reference that could not be resolved.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DanglingRef {
+    pub collection: Key, // collection of the referring memo
+    pub title: String,   // title of the referring memo
+    pub key: Key,        // key of the referring node or attribute
+    pub target: Value
+}
+
+impl std::fmt::Display for DanglingRef {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "@{} {}: .{} refers to unknown memo '{}'",
+               self.collection, self.title, self.key, self.target)
+    }
+}
+
+/// A memo whose id is already used by another memo.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DuplicateId {
+    pub id: MemoId,
+    pub collection: Key,       // collection of the memo
+    pub title: String,         // title of the memo
+    pub first_collection: Key, // collection of the memo using the id first
+    pub first_title: String    // title of the memo using the id first
+}
+
+impl std::fmt::Display for DuplicateId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "@{} {}: id '{}' is already used by @{} {}",
+               self.collection, self.title, self.id, self.first_collection, self.first_title)
+    }
+}
+
+// Returns the values of the given node and its attributes, together
+// with the key of the node or attribute.
+fn values_of(node: &Node) -> impl Iterator<Item=(&Key, &Value)> {
+    std::iter::once((&node.key, &node.value))
+        .chain(node.attrs().iter().map(|(key, value)| (key, value)))
+}
+
+/// Turns references to collections that none of the given memos
+/// belongs to back into text, e.g. `@home sweet home`.
+pub fn demote_refs(memos: &mut [Memo]) {
+    let collections: HashSet<Key> = memos.iter().map(|memo| memo.collection()).collect();
+    let demote = |node: &mut Node| {
+        let values = std::iter::once(&mut node.value)
+            .chain(node.attrs.iter_mut().map(|(_, value)| value));
+        for value in values {
+            if let Value::Ref(collection, _) = value {
+                if !collections.contains(collection) {
+                    *value = Value::Text(value.to_string());
+                }
+            }
+        }
+    };
+    for memo in memos.iter_mut() {
+        demote(memo.header_mut());
+        memo.data_mut().for_each(demote);
+    }
+}
+
+/// Returns all references in node values and attributes of the given
+/// memos that cannot be resolved.
+pub fn dangling_refs(memos: &[Memo]) -> Vec<DanglingRef> {
+    // look up the targets once instead of resolving each reference
+    let titles: HashSet<(Key, String)> = memos.iter()
+        .map(|memo| (memo.collection(), memo.title()))
+        .collect();
+    let ids: HashSet<MemoId> = memos.iter().map(|memo| memo.id()).collect();
+    let exists = |value: &Value| match value {
+        Value::Ref(collection, title) => titles.contains(&(collection.clone(), title.clone())),
+        Value::RefById(id) => ids.contains(id),
+        _ => true
+    };
+
+    let mut dangling = vec!();
+    for memo in memos {
+        for node in memo.nodes() {
+            for (key, value) in values_of(node) {
+                if !exists(value) {
+                    dangling.push(DanglingRef {
+                        collection: memo.collection(),
+                        title: memo.title(),
+                        key: key.clone(),
+                        target: value.clone()
+                    });
+                }
+            }
+        }
+    }
+    dangling
+}
+
+/// Returns all memos whose id, either given by `+id` or generated
+/// from collection and title, is already used by a previous memo.
+/// Include memos are skipped, as several files may include the same
+/// file.
+pub fn duplicate_ids(memos: &[Memo]) -> Vec<DuplicateId> {
+    let mut first: HashMap<MemoId, &Memo> = HashMap::new();
+    let mut duplicates = vec!();
+    for memo in memos.iter().filter(|memo| memo.collection() != "mr:include") {
+        let id = memo.id();
+        match first.get(&id) {
+            Some(other) => duplicates.push(DuplicateId {
+                id,
+                collection: memo.collection(),
+                title: memo.title(),
+                first_collection: other.collection(),
+                first_title: other.title()
+            }),
+            None => {
+                first.insert(id, memo);
+            }
+        }
+    }
+    duplicates
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sample::setup_memos;
+
+    #[test]
+    fn test_resolve() {
+        let memos = setup_memos();
+        let book = &memos[0];
+        let author = resolve(&memos, &book.get("author").unwrap().value).unwrap();
+        assert_eq!(author.collection(), "author");
+        assert_eq!(author.title(), "J.R.R. Tolkien");
+
+        assert!(resolve(&memos, &Value::from("J.R.R. Tolkien")).is_none());
+        assert!(resolve(&memos, &Value::Ref("author".into(), "Tolkien".into())).is_none());
+    }
+
+    #[test]
+    fn test_resolve_by_id() {
+        let memos = vec!(
+            Memo::new("author", "J.R.R. Tolkien").with_attr("id", "tolkien"),
+            Memo::new("book", "The Hobbit")
+                .with(("author", Value::infer("@#tolkien")))
+                .with(("publisher", Value::infer("@#unwin")))
+        );
+        let book = &memos[1];
+        let author = resolve(&memos, &book.get("author").unwrap().value).unwrap();
+        assert_eq!(author.title(), "J.R.R. Tolkien");
+        assert_eq!(find_by_id(&memos, &book.id()).unwrap().title(), "The Hobbit");
+
+        let dangling = dangling_refs(&memos);
+        assert_eq!(dangling.len(), 1);
+        assert_eq!(dangling[0].to_string(),
+                   "@book The Hobbit: .publisher refers to unknown memo '@#unwin'");
+    }
+
+    #[test]
+    fn test_demote_refs() {
+        let mut memos = vec!(
+            Memo::new("book", "The Hobbit")
+                .with(("author", Value::infer("@author J.R.R. Tolkien")))
+                .with(("motto", Value::infer("@home sweet home")))
+                .with_attr("see", Value::infer("@book Unfinished Tales")),
+            Memo::new("author", "J.R.R. Tolkien")
+        );
+        demote_refs(&mut memos);
+        let book = &memos[0];
+        assert!(book.get("author").unwrap().value.is_ref());
+        assert_eq!(book.get("motto").unwrap().value, Value::from("@home sweet home"));
+        assert!(book.get("motto").unwrap().get_attr("see").unwrap().is_ref());
+    }
+
+    #[test]
+    fn test_dangling_refs() {
+        assert_eq!(dangling_refs(&setup_memos()), vec!());
+
+        let memos = vec!(
+            Memo::new("book", "The Hobbit")
+                .with(("author", Value::Ref("author".into(), "J.R.R. Tolkien".into())))
+                .with(("character", "Bilbo Baggins"))
+                .with_attr("friend-of", Value::Ref("character".into(), "Gandalf".into())),
+            Memo::new("author", "J.R.R. Tolkien")
+        );
+        let dangling = dangling_refs(&memos);
+        assert_eq!(dangling.len(), 1);
+        assert_eq!(dangling[0].key, "friend-of");
+        assert_eq!(dangling[0].to_string(),
+                   "@book The Hobbit: .friend-of refers to unknown memo '@character Gandalf'");
+    }
+
+    #[test]
+    fn test_duplicate_ids() {
+        assert_eq!(duplicate_ids(&setup_memos()), vec!());
+
+        let memos = vec!(
+            Memo::new("author", "J.R.R. Tolkien").with_attr("id", "tolkien"),
+            Memo::new("author", "Christopher Tolkien").with_attr("id", "tolkien"),
+            Memo::new("book", "The Hobbit"),
+            Memo::new("book", "The Hobbit").with(("edition", 2)),
+            Memo::new("mr:include", "common.mr"),
+            Memo::new("mr:include", "common.mr")
+        );
+        let duplicates = duplicate_ids(&memos);
+        assert_eq!(duplicates.len(), 2);
+        assert_eq!(duplicates[0].to_string(),
+                   "@author Christopher Tolkien: id 'tolkien' is already used by @author J.R.R. Tolkien");
+        assert_eq!(duplicates[1].id, memos[2].id());
+        assert_eq!(duplicates[1].first_title, "The Hobbit");
+    }
+}
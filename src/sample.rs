@@ -1,16 +1,22 @@
 use crate::memo::Memo;
+use crate::value::Value;
+
+// shorthand for a reference to another memo
+fn to(collection: &str, title: &str) -> Value {
+    Value::Ref(collection.into(), title.into())
+}
 
 pub fn setup_memos() -> Vec<Memo> {
     let mut memos: Vec<Memo> = vec!();
         
     let memo = Memo::new("book", "The Lord of the Rings")
-        .with(("author", "J.R.R. Tolkien"))
-        .with(("character", "Bilbo Baggins"))
-        .with(("character", "Samweis Gamdschie"))
-        .with(("character", "Frodo Baggins"))
-        .with(("character", "Aragorn"))
-        .with(("character", "Legolas"))
-        .with(("character", "Galadriel"));
+        .with(("author", to("author", "J.R.R. Tolkien")))
+        .with(("character", to("character", "Bilbo Baggins")))
+        .with(("character", to("character", "Samwise Gamgee")))
+        .with(("character", to("character", "Frodo Baggins")))
+        .with(("character", to("character", "Aragorn")))
+        .with(("character", to("character", "Legolas")))
+        .with(("character", to("character", "Galadriel")));
     memos.push(memo);
 
     let memo = Memo::new("author", "J.R.R. Tolkien")
@@ -19,7 +25,7 @@ pub fn setup_memos() -> Vec<Memo> {
 
     let memo = Memo::new("character", "Bilbo Baggins")
         .with(("species", "hobbit"))
-        .with(("friend-of", "Samweis Gamdschie"))
+        .with(("friend-of", to("character", "Samwise Gamgee")))
         .with(("is-hobbit", true));
     memos.push(memo);
 
@@ -48,9 +54,9 @@ pub fn setup_memos() -> Vec<Memo> {
     memos.push(memo);
         
     let memo = Memo::new("book", "The Hitchhiker's Guide to the Galaxy")
-        .with(("author", "Douglas Adams"))
-        .with(("character", "Arthur Dent"))
-        .with(("character", "Ford Prefect"));        
+        .with(("author", to("author", "Douglas Adams")))
+        .with(("character", to("character", "Arthur Dent")))
+        .with(("character", to("character", "Ford Prefect")));
     memos.push(memo);
 
     let memo = Memo::new("author", "Douglas Adams")
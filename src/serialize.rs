@@ -0,0 +1,221 @@
+//! Serialization with serde (cargo feature `serde`).
+//!
+//! `Memo`, `Node`, `Value` and `NodeType` implement `Serialize` and
+//! `Deserialize`.  The shape of the data is part of the public
+//! interface and will only change with a new major version.  In
+//! JSON, the memo
+//!
+//! ```text
+//! # the most common metal
+//! @element Fe
+//! .amu 55.845
+//! .density 7.87
+//! +unit g/cm³
+//! ```
+//!
+//! is represented as
+//!
+//! ```json
+//! {
+//!   "nodes": [
+//!     { "key": "element", "value": { "type": "text", "value": "Fe" },
+//!       "attrs": [], "comments": [" the most common metal"] },
+//!     { "key": "amu", "value": { "type": "float", "value": 55.845, "text": "55.845" },
+//!       "attrs": [] },
+//!     { "key": "density", "value": { "type": "float", "value": 7.87, "text": "7.87" },
+//!       "attrs": [["unit", { "type": "text", "value": "g/cm³" }]] }
+//!   ],
+//!   "path": "data/periodic.mr"
+//! }
+//! ```
+//!
+//! A memo is the list of its nodes, the first one being the header
+//! node, and optionally the path of the file it was read from.  A
+//! node consists of key, value, the attributes as list of key-value
+//! pairs and optionally the comment lines before the node.  Nodes
+//! inherited from a prototype have the member `"inherited": true`,
+//! inherited attributes are listed by key in `inherited_attrs`.
+//! Source locations are not serialized.
+//!
+//! A value is an object whose member `type` determines the other
+//! members:
+//!
+//! | type          | members                                  | example        |
+//! |---------------|------------------------------------------|----------------|
+//! | `text`        | `value`                                  | `Fe`           |
+//! | `multiline`   | `value`, `sep` (separator in the file)   | `<<EOF`        |
+//! | `integer`     | `value`                                  | `26`           |
+//! | `float`       | `value`, `text` (as written, optional)   | `55.845`       |
+//! | `bool`        | `value`                                  | `true`         |
+//! | `date`        | `value` as string                        | `1808-06-30`   |
+//! | `datetime`    | `value` as string                        | `2021-01-01T12:00:00Z` |
+//! | `time`        | `value` as string                        | `12:00`        |
+//! | `ref`         | `collection`, `title`                    | `@author Tolkien` |
+//! | `ref_by_id`   | `id`                                     | `@#tolkien`    |
+//! | `quantity`    | `value` (a value), `unit`                | `2.35 T`       |
+//! | `range`       | `from`, `to` (values), `sep` (optional)  | `1.8 - 2.1`    |
+//!
+//! `NodeType` is one of the strings `header`, `data` and `any`.
+//!
+
+use std::convert::TryFrom;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{Memo, Node, Value, Key};
+
+// The representation of a Value, see the table above.
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub(crate) enum ValueRepr {
+    Text { value: String },
+    #[serde(rename = "multiline")]
+    MultiLineText { value: String, sep: String },
+    Integer { value: i64 },
+    Float { value: f64, #[serde(default)] text: Option<String> },
+    Bool { value: bool },
+    Date { value: String },
+    #[serde(rename = "datetime")]
+    DateTime { value: String },
+    Time { value: String },
+    Ref { collection: Key, title: String },
+    RefById { id: String },
+    Quantity { value: Box<Value>, unit: String },
+    Range { from: Box<Value>, to: Box<Value>, #[serde(default)] sep: Option<String> }
+}
+
+impl From<Value> for ValueRepr {
+    fn from(value: Value) -> ValueRepr {
+        match value {
+            Value::Text(value) => ValueRepr::Text { value },
+            Value::MultiLineText(value, sep) => ValueRepr::MultiLineText { value, sep },
+            Value::Integer(value) => ValueRepr::Integer { value },
+            Value::Float(value, text) => ValueRepr::Float { value, text: Some(text) },
+            Value::Bool(value) => ValueRepr::Bool { value },
+            Value::Date(date) => ValueRepr::Date { value: date.to_string() },
+            Value::DateTime(dt) => ValueRepr::DateTime { value: dt.to_string() },
+            Value::Time(time) => ValueRepr::Time { value: time.to_string() },
+            Value::Ref(collection, title) => ValueRepr::Ref { collection, title },
+            Value::RefById(id) => ValueRepr::RefById { id },
+            Value::Quantity(value, unit) => ValueRepr::Quantity { value, unit },
+            Value::Range(from, to, sep) => ValueRepr::Range { from, to, sep: Some(sep) }
+        }
+    }
+}
+
+impl TryFrom<ValueRepr> for Value {
+    type Error = &'static str;
+
+    fn try_from(repr: ValueRepr) -> Result<Value, Self::Error> {
+        Ok(match repr {
+            ValueRepr::Text { value } => Value::Text(value),
+            ValueRepr::MultiLineText { value, sep } => Value::MultiLineText(value, sep),
+            ValueRepr::Integer { value } => Value::Integer(value),
+            ValueRepr::Float { value, text } => {
+                let text = text.unwrap_or_else(|| value.to_string());
+                Value::Float(value, text)
+            },
+            ValueRepr::Bool { value } => Value::Bool(value),
+            ValueRepr::Date { value } => Value::Date(value.parse()?),
+            ValueRepr::DateTime { value } => Value::DateTime(value.parse()?),
+            ValueRepr::Time { value } => Value::Time(value.parse()?),
+            ValueRepr::Ref { collection, title } => Value::Ref(collection, title),
+            ValueRepr::RefById { id } => Value::RefById(id),
+            ValueRepr::Quantity { value, unit } => Value::Quantity(value, unit),
+            ValueRepr::Range { from, to, sep } =>
+                Value::Range(from, to, sep.unwrap_or_else(|| " - ".into()))
+        })
+    }
+}
+
+// The representation of a Memo, which must have a header node.
+#[derive(Deserialize)]
+pub(crate) struct MemoRepr {
+    nodes: Vec<Node>,
+    #[serde(default)]
+    path: Option<std::path::PathBuf>,
+    #[serde(default)]
+    trailing_comments: Vec<String>
+}
+
+impl TryFrom<MemoRepr> for Memo {
+    type Error = &'static str;
+
+    fn try_from(repr: MemoRepr) -> Result<Memo, Self::Error> {
+        let mut nodes = repr.nodes.into_iter();
+        let header = nodes.next().ok_or("memo without header node")?;
+        let mut memo = Memo::new(header.key.as_str(), header.value.clone());
+        *memo.header_mut() = header;
+        for node in nodes {
+            memo.push(node);
+        }
+        if let Some(path) = repr.path {
+            memo.set_path(path);
+        }
+        memo.set_trailing_comments(repr.trailing_comments);
+        Ok(memo)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memo::NodeType;
+    use crate::parser::read_from_str;
+    use crate::sample::setup_memos;
+
+    #[test]
+    fn json_shape() {
+        let memo = Memo::new("element", "Fe")
+            .with(Node::new("amu", Value::infer("55.845")).with_comment(" standard"))
+            .with(("density", Value::infer("7.87")))
+            .with_attr("unit", "g/cm³");
+        let json = serde_json::to_value(&memo).unwrap();
+        assert_eq!(json, serde_json::json!({
+            "nodes": [
+                { "key": "element", "value": { "type": "text", "value": "Fe" }, "attrs": [] },
+                { "key": "amu", "value": { "type": "float", "value": 55.845, "text": "55.845" },
+                  "attrs": [], "comments": [" standard"] },
+                { "key": "density", "value": { "type": "float", "value": 7.87, "text": "7.87" },
+                  "attrs": [["unit", { "type": "text", "value": "g/cm³" }]] }
+            ]
+        }));
+
+        let json = serde_json::to_value(Value::infer("1.8 - 2.1 g/cm³")).unwrap();
+        assert_eq!(json["type"], "range");
+        assert_eq!(json["to"]["type"], "quantity");
+        assert_eq!(json["to"]["unit"], "g/cm³");
+        let range: Value = serde_json::from_value(serde_json::to_value(Value::infer("1.8–2.1")).unwrap()).unwrap();
+        assert_eq!(range.to_string(), "1.8–2.1");
+        assert_eq!(serde_json::to_value(Value::infer("1808-06")).unwrap(),
+                   serde_json::json!({ "type": "date", "value": "1808-06" }));
+        assert_eq!(serde_json::to_value(Value::infer("@#tolkien")).unwrap(),
+                   serde_json::json!({ "type": "ref_by_id", "id": "tolkien" }));
+        assert_eq!(serde_json::to_value(NodeType::Header).unwrap(), "header");
+    }
+
+    #[test]
+    fn json_round_trip() {
+        let input = "# elements\n@element Fe\n+id fe\n.amu ~55.8\n.doc<<END\nfirst\nsecond\nEND\n.discovery 1808-06-30\n.author @author Cavendish\n.flag true\n.ref @#h\n";
+        let mut memos = read_from_str(input).unwrap();
+        memos[0].set_path("data/sample.mr");
+        memos.extend(setup_memos());
+
+        let json = serde_json::to_string(&memos).unwrap();
+        let result: Vec<Memo> = serde_json::from_str(&json).unwrap();
+        assert_eq!(result, memos);
+        assert_eq!(result[0].path(), memos[0].path());
+        assert_eq!(result[0].comments(), [" elements"]);
+        assert_eq!(result[0].get("amu").unwrap().value.to_string(), "~55.8");
+    }
+
+    #[test]
+    fn json_errors() {
+        assert!(serde_json::from_str::<Memo>(r#"{ "nodes": [] }"#).is_err());
+        assert!(serde_json::from_str::<Value>(r#"{ "type": "date", "value": "1808-13" }"#).is_err());
+        assert!(serde_json::from_str::<Value>(r#"{ "type": "unknown" }"#).is_err());
+        let value: Value = serde_json::from_str(r#"{ "type": "float", "value": 1.5 }"#).unwrap();
+        assert_eq!(value.to_string(), "1.5");
+    }
+}
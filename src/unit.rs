@@ -0,0 +1,182 @@
+//! Physical units for quantity values such as `2.35 T` or `7.87 g/cm³`.
+//!
+//! A Unit is described by its dimension, i.e. the exponents of the
+//! SI base units (m, kg, s, A, K, mol, cd), and by the factor that
+//! converts a value into SI base units.  Two units are compatible if
+//! they have the same dimension, e.g. `g/cm³` and `kg/m³`.
+//!
+//! A unit is written as a product of known unit symbols, optionally
+//! with an exponent, e.g. `m²`, `m^2` or `m^-1`.  Symbols are
+//! separated by `·`, `*` or `/`, where every symbol after a `/` is
+//! part of the denominator, e.g. `J/mol·K` means J/(mol·K).
+//!
+
+/// Exponents of the SI base units (m, kg, s, A, K, mol, cd).
+pub type Dimension = [i8; 7];
+
+/// A physical unit, relative to the SI base units.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Unit {
+    pub factor: f64,
+    pub dimension: Dimension
+}
+
+const M: Dimension = [1, 0, 0, 0, 0, 0, 0];
+const KG: Dimension = [0, 1, 0, 0, 0, 0, 0];
+const S: Dimension = [0, 0, 1, 0, 0, 0, 0];
+const A: Dimension = [0, 0, 0, 1, 0, 0, 0];
+const K: Dimension = [0, 0, 0, 0, 1, 0, 0];
+const MOL: Dimension = [0, 0, 0, 0, 0, 1, 0];
+const CD: Dimension = [0, 0, 0, 0, 0, 0, 1];
+const L3: Dimension = [3, 0, 0, 0, 0, 0, 0];           // volume
+const HZ: Dimension = [0, 0, -1, 0, 0, 0, 0];          // frequency
+const N: Dimension = [1, 1, -2, 0, 0, 0, 0];           // force
+const PA: Dimension = [-1, 1, -2, 0, 0, 0, 0];         // pressure
+const J: Dimension = [2, 1, -2, 0, 0, 0, 0];           // energy
+const W: Dimension = [2, 1, -3, 0, 0, 0, 0];           // power
+const V: Dimension = [2, 1, -3, -1, 0, 0, 0];          // voltage
+const OHM: Dimension = [2, 1, -3, -2, 0, 0, 0];        // resistance
+const T: Dimension = [0, 1, -2, -1, 0, 0, 0];          // magnetic flux density
+
+// Known unit symbols with their factor and dimension.  Prefixed
+// units are listed explicitly, so that e.g. `min` or `cd` are not
+// mistaken for prefixed units.
+const UNITS: &[(&str, f64, Dimension)] = &[
+    // length
+    ("m", 1.0, M), ("km", 1e3, M), ("cm", 1e-2, M), ("mm", 1e-3, M),
+    ("µm", 1e-6, M), ("um", 1e-6, M), ("nm", 1e-9, M), ("pm", 1e-12, M),
+    ("Å", 1e-10, M),
+    // mass
+    ("kg", 1.0, KG), ("g", 1e-3, KG), ("mg", 1e-6, KG), ("µg", 1e-9, KG),
+    ("t", 1e3, KG), ("u", 1.66053906660e-27, KG), ("Da", 1.66053906660e-27, KG),
+    // time
+    ("s", 1.0, S), ("ms", 1e-3, S), ("µs", 1e-6, S), ("ns", 1e-9, S),
+    ("min", 60.0, S), ("h", 3600.0, S), ("d", 86400.0, S),
+    // electric current
+    ("A", 1.0, A), ("mA", 1e-3, A),
+    // temperature
+    ("K", 1.0, K),
+    // amount of substance
+    ("mol", 1.0, MOL), ("mmol", 1e-3, MOL),
+    // luminous intensity
+    ("cd", 1.0, CD),
+    // volume
+    ("l", 1e-3, L3), ("L", 1e-3, L3), ("ml", 1e-6, L3), ("mL", 1e-6, L3),
+    // frequency
+    ("Hz", 1.0, HZ), ("kHz", 1e3, HZ), ("MHz", 1e6, HZ), ("GHz", 1e9, HZ),
+    // force
+    ("N", 1.0, N), ("kN", 1e3, N),
+    // pressure
+    ("Pa", 1.0, PA), ("hPa", 1e2, PA), ("kPa", 1e3, PA), ("MPa", 1e6, PA),
+    ("GPa", 1e9, PA), ("bar", 1e5, PA), ("mbar", 1e2, PA), ("atm", 101325.0, PA),
+    // energy
+    ("J", 1.0, J), ("kJ", 1e3, J), ("MJ", 1e6, J),
+    ("eV", 1.602176634e-19, J), ("keV", 1.602176634e-16, J), ("MeV", 1.602176634e-13, J),
+    ("cal", 4.184, J), ("kcal", 4184.0, J),
+    // power
+    ("W", 1.0, W), ("kW", 1e3, W), ("MW", 1e6, W),
+    // electricity
+    ("V", 1.0, V), ("kV", 1e3, V), ("mV", 1e-3, V), ("Ω", 1.0, OHM),
+    // magnetic flux density
+    ("T", 1.0, T), ("mT", 1e-3, T), ("G", 1e-4, T),
+];
+
+// Split a unit factor such as `cm³` or `m^-1` into symbol and exponent.
+fn split_exponent(s: &str) -> Option<(&str, i8)> {
+    if let Some(pos) = s.find('^') {
+        let exponent = s[pos + 1..].parse::<i8>().ok()?;
+        return Some((&s[..pos], exponent));
+    }
+
+    let pos = s.find(|c| "⁻¹²³⁴⁵⁶⁷⁸⁹⁰".contains(c)).unwrap_or(s.len());
+    if pos == s.len() {
+        return Some((s, 1));
+    }
+    let exponent = s[pos..].chars()
+        .map(|c| match c {
+            '⁻' => '-', '¹' => '1', '²' => '2', '³' => '3', '⁴' => '4',
+            '⁵' => '5', '⁶' => '6', '⁷' => '7', '⁸' => '8', '⁹' => '9',
+            _ => '0'
+        })
+        .collect::<String>()
+        .parse::<i8>().ok()?;
+    Some((&s[..pos], exponent))
+}
+
+impl Unit {
+    /// The unit of a dimensionless number.
+    pub fn one() -> Unit {
+        Unit { factor: 1.0, dimension: [0; 7] }
+    }
+
+    /// Parses the given unit, e.g. `g/cm³`.  Returns None if the
+    /// unit contains an unknown symbol.
+    pub fn parse(s: &str) -> Option<Unit> {
+        let mut unit = Unit::one();
+        for (n, part) in s.split('/').enumerate() {
+            // everything after the first '/' is in the denominator
+            let sign = if n == 0 { 1 } else { -1 };
+            for factor in part.split(['·', '*', '⋅']) {
+                let (symbol, exponent) = split_exponent(factor.trim())?;
+                let (_, f, dimension) = UNITS.iter().find(|(sym, _, _)| *sym == symbol)?;
+                let exponent = sign * exponent;
+                unit.factor *= f.powi(exponent as i32);
+                for (d, e) in unit.dimension.iter_mut().zip(dimension.iter()) {
+                    *d += e * exponent;
+                }
+            }
+        }
+        Some(unit)
+    }
+
+    /// Returns true if values can be converted between both units.
+    pub fn is_compatible(&self, other: &Unit) -> bool {
+        self.dimension == other.dimension
+    }
+
+    /// Converts the value `x`, given in this unit, into the unit
+    /// `other`.  Returns None if the units are not compatible.
+    pub fn convert(&self, x: f64, other: &Unit) -> Option<f64> {
+        match self.is_compatible(other) {
+            true => Some(x * self.factor / other.factor),
+            false => None
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(a: f64, b: f64) {
+        assert!((a - b).abs() <= 1e-9 * b.abs().max(1.0), "{} != {}", a, b);
+    }
+
+    #[test]
+    fn parse_unit() {
+        assert_eq!(Unit::parse("T"), Some(Unit { factor: 1.0, dimension: T }));
+        assert_eq!(Unit::parse("g/cm³").unwrap().dimension, [-3, 1, 0, 0, 0, 0, 0]);
+        assert_eq!(Unit::parse("kg/m^3").unwrap().dimension, [-3, 1, 0, 0, 0, 0, 0]);
+        assert_eq!(Unit::parse("J/mol·K").unwrap().dimension, [2, 1, -2, 0, -1, -1, 0]);
+        assert_eq!(Unit::parse("m·s⁻¹"), Unit::parse("m/s"));
+
+        assert_eq!(Unit::parse("Monkeys"), None);
+        assert_eq!(Unit::parse("g/"), None);
+        assert_eq!(Unit::parse(""), None);
+    }
+
+    #[test]
+    fn convert_unit() {
+        let g_cm3 = Unit::parse("g/cm³").unwrap();
+        let kg_m3 = Unit::parse("kg/m³").unwrap();
+        assert_close(g_cm3.convert(7.87, &kg_m3).unwrap(), 7870.0);
+        assert_close(kg_m3.convert(7870.0, &g_cm3).unwrap(), 7.87);
+
+        let t = Unit::parse("T").unwrap();
+        let mt = Unit::parse("mT").unwrap();
+        assert_close(t.convert(2.35, &mt).unwrap(), 2350.0);
+
+        assert_eq!(t.convert(1.0, &g_cm3), None);
+    }
+}
@@ -4,37 +4,235 @@
 //! Possible variants are:
 //! - `Value::Text` holding a single-line string (no newlines)
 //! - `Value::MultiLineText` holding a multi-line string (with newlines)
-//! - `Value::Integer` holding a 32-bit integer number
-//! - `Value::Float` holding a 32-bit floating point number
+//! - `Value::Integer` holding a 64-bit integer number
+//! - `Value::Float` holding a 64-bit floating point number together
+//!   with its original notation (lexeme), e.g. `118.710`, which may
+//!   include an uncertainty (`4.002602(2)`) or mark the number as
+//!   approximate (`~1.8`, `[209]`)
 //! - `Value::Bool` holding a true/false value
+//! - `Value::Date` holding a calendar date, e.g. `1892-01-03`
+//! - `Value::DateTime` holding a date and a time, e.g. `2021-01-24T14:30`
+//! - `Value::Time` holding a time of day, e.g. `14:30`
+//! - `Value::Ref` holding a reference to another memo, given by
+//!   collection and title, e.g. `@author J.R.R. Tolkien`
+//! - `Value::RefById` holding a reference to another memo, given by
+//!   its id (see `Memo::id`), e.g. `@#tolkien`
+//! - `Value::Quantity` holding a number with a physical unit,
+//!   e.g. `2.35 T`
+//! - `Value::Range` holding an interval of two numbers, e.g. `1.8 - 2.1`,
+//!   together with the separator as written, e.g. ` - ` or `–`
 //!
 //! A Value can be constructed using the From Trait:
 //! - String or &str will be converted into `Value::Text` or to
 //!   `Value::MultiLineText` if it contains at least one newline.
-//! - i32 will be converted into `Value::Integer`
-//! - f32 will be converted into `Value::Float`
+//! - i32 and i64 will be converted into `Value::Integer`
+//! - f32 and f64 will be converted into `Value::Float`
 //! - bool will be converted into `Value::Bool`
+//! - Date, DateTime and Time will be converted into the
+//!   corresponding temporal variant
+//!
+//! Values read from a file are constructed using `Value::infer`,
+//! which recognises numbers, booleans, ISO-8601 dates and times as
+//! well as references.
+//!
+//! Values are totally ordered, so that memos and nodes can be sorted
+//! by value.  Values of different kinds are ordered as booleans <
+//! numbers < ranges < dates < times < text < references.  Numbers
+//! are ordered numerically (quantities after conversion into SI base
+//! units).  Text uses a natural sort order, so that `item 9` comes
+//! before `item 10`.
+//!
+//! Two values are only equal if they are written the same way, so
+//! `127.60` comes right after `127.6`, but is not equal to it.
 //!
 
-// TODO: Value::Date, Value::DateTime, Value::Time
-// TODO: Value::Ref(group, title), Value::RefById(id)
-
+use std::cmp::Ordering;
 use std::convert::TryFrom;
+use std::str::FromStr;
+
+use crate::date::{Date, DateTime, Time};
+use crate::unit::Unit;
 
 pub type Key = String;
 
 /// A Value is an enum for the different types of values that a
 /// Node can hold.
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize),
+           serde(into = "crate::serialize::ValueRepr",
+                 try_from = "crate::serialize::ValueRepr"))]
 pub enum Value {
     Text(String),
     MultiLineText(String, String), // (text, sep)
-    Integer(i32),
-    Float(f32),
-    Bool(bool)
+    Integer(i64),
+    Float(f64, String), // (value, lexeme)
+    Bool(bool),
+    Date(Date),
+    DateTime(DateTime),
+    Time(Time),
+    Ref(Key, String), // (collection, title)
+    RefById(String), // id
+    Quantity(Box<Value>, String), // (number, unit)
+    Range(Box<Value>, Box<Value>, String) // (from, to, sep)
 }
 
 impl Value {
+    /// Constructs a Value from the given text as it appears in a
+    /// file.  The following kinds of single-line text are recognised:
+    ///
+    /// - integer numbers, e.g. `42` or `-7`, as `Value::Integer`
+    /// - decimal numbers, e.g. `1.008` or `6.02e23`, as `Value::Float`
+    /// - a number followed by a known unit, e.g. `2.35 T`, as
+    ///   `Value::Quantity` (see `unit.rs` for the known units)
+    /// - two numbers or quantities separated by ` - ` or `–`, e.g.
+    ///   `1.8 - 2.1`, as `Value::Range`
+    /// - `true`/`false` and `yes`/`no` as `Value::Bool`
+    /// - ISO-8601 notation as `Value::Date`, `Value::DateTime` or
+    ///   `Value::Time`.  A single year such as `1808` is an integer.
+    /// - `@collection title` as `Value::Ref`
+    /// - `@#id` as `Value::RefById`
+    ///
+    /// Decimal numbers keep their notation, so that `118.710` is
+    /// printed back unchanged.  Integers with leading zeros or an
+    /// explicit sign, e.g. `007` or `+1`, remain text.  Everything
+    /// else is converted using `Value::from`.
+    pub fn infer(s: &str) -> Value {
+        if let Some(value) = Value::parse_ref(s) {
+            return value;
+        }
+        if let Some(b) = Value::parse_bool(s) {
+            return Value::Bool(b);
+        }
+        if let Some(value) = Value::parse_number(s) {
+            return value;
+        }
+        if let Some(value) = Value::parse_quantity(s) {
+            return value;
+        }
+        if let Some(value) = Value::parse_range(s) {
+            return value;
+        }
+        if let Ok(date) = Date::from_str(s) {
+            if date.month.is_some() {
+                return Value::Date(date);
+            }
+        } else if let Ok(dt) = DateTime::from_str(s) {
+            return Value::DateTime(dt);
+        } else if let Ok(time) = Time::from_str(s) {
+            return Value::Time(time);
+        }
+        Value::from(s)
+    }
+
+    /// Returns the boolean value of the given text, which must be
+    /// one of `true`, `false`, `yes` or `no`.
+    pub fn parse_bool(s: &str) -> Option<bool> {
+        match s {
+            "true" | "yes" => Some(true),
+            "false" | "no" => Some(false),
+            _ => None
+        }
+    }
+
+    // Parse an integer or decimal number.  Only the notation
+    // `-?digits(.digits)?([eE][+-]?digits)?` is accepted, so that
+    // words such as `inf` or `NaN` are rejected, even though they
+    // would be accepted by `str::parse`.
+    fn parse_number(s: &str) -> Option<Value> {
+        // approximate numbers and numbers with uncertainty are always
+        // floats, keeping their notation
+        let number = if let Some(x) = s.strip_prefix('~') {
+            x.to_string()
+        } else if let Some(x) = s.strip_prefix('[').and_then(|x| x.strip_suffix(']')) {
+            x.to_string()
+        } else if let Some((mantissa, _, exponent)) = split_uncertainty(s) {
+            format!("{}{}", mantissa, exponent)
+        } else {
+            return Value::parse_plain_number(s);
+        };
+        let x = f64::try_from(&Value::parse_plain_number(&number)?).ok()?;
+        Some(Value::Float(x, s.to_string()))
+    }
+
+    // Parse a number without uncertainty, e.g. `-0.5` or `6.02e23`.
+    fn parse_plain_number(s: &str) -> Option<Value> {
+        let (mantissa, exponent) = match s.find(['e', 'E']) {
+            Some(pos) => (&s[..pos], Some(&s[pos + 1..])),
+            None => (s, None)
+        };
+        let (int, frac) = match mantissa.find('.') {
+            Some(pos) => (&mantissa[..pos], Some(&mantissa[pos + 1..])),
+            None => (mantissa, None)
+        };
+
+        let is_digits = |s: &str| !s.is_empty() && s.chars().all(|c| c.is_ascii_digit());
+        let int = int.strip_prefix('-').unwrap_or(int);
+        let exponent = exponent.map(|e| e.strip_prefix(['+', '-']).unwrap_or(e));
+        if !is_digits(int) || !frac.is_none_or(is_digits) || !exponent.is_none_or(is_digits) {
+            return None;
+        }
+
+        if frac.is_none() && exponent.is_none() {
+            let n = s.parse::<i64>().ok()?;
+            Some(Value::Integer(n)).filter(|_| n.to_string() == s)
+        } else {
+            let x = s.parse::<f64>().ok()?;
+            Some(Value::Float(x, s.to_string())).filter(|_| x.is_finite())
+        }
+    }
+
+    // Parse a number followed by a unit, e.g. `2.35 T`, into a
+    // Value::Quantity.
+    fn parse_quantity(s: &str) -> Option<Value> {
+        let (number, unit) = s.split_at(s.find(' ')?);
+        let number = Value::parse_number(number)?;
+        let unit = unit.trim_start();
+        Unit::parse(unit)?;
+        Some(Value::Quantity(Box::new(number), unit.into()))
+    }
+
+    // Parse two numbers separated by ` - ` or by an en dash into a
+    // Value::Range, e.g. `1.8 - 2.1` or `1.8–2.1 g/cm³`.
+    fn parse_range(s: &str) -> Option<Value> {
+        let (start, end) = match s.find(" - ") {
+            Some(pos) => (pos, pos + 3),
+            None => {
+                // the separator includes the spaces around `–`
+                let pos = s.find('–')?;
+                let end = pos + '–'.len_utf8();
+                (s[..pos].trim_end().len(), s.len() - s[end..].trim_start().len())
+            }
+        };
+        let bound = |s: &str| Value::parse_number(s.trim())
+            .or_else(|| Value::parse_quantity(s.trim()));
+        Some(Value::Range(Box::new(bound(&s[..start])?), Box::new(bound(&s[end..])?),
+                          s[start..end].into()))
+    }
+
+    // Parse `@collection title` into a Value::Ref and `@#id` into a
+    // Value::RefById.  The collection must be a valid key, see `key`
+    // in memo.pest.
+    fn parse_ref(s: &str) -> Option<Value> {
+        let s = s.strip_prefix('@')?;
+        if let Some(id) = s.strip_prefix('#') {
+            let is_id = !id.is_empty()
+                && id.chars().all(|c| c.is_alphanumeric() || "_:/.-".contains(c));
+            return Some(Value::RefById(id.into())).filter(|_| is_id);
+        }
+        let (collection, title) = s.split_at(s.find(char::is_whitespace)?);
+        let title = title.trim();
+
+        let mut chars = collection.chars();
+        let is_key = chars.next()?.is_alphabetic()
+            && chars.all(|c| c.is_alphanumeric() || "_:/.-".contains(c));
+
+        if is_key && !title.is_empty() && !title.contains('\n') {
+            Some(Value::Ref(collection.into(), title.into()))
+        } else {
+            None
+        }
+    }
+
     /// Returns true if Value is of type Value::Bool.
     pub fn is_bool(&self) -> bool {
         match self {
@@ -43,10 +241,12 @@ impl Value {
         }
     }
 
-    /// Returns true if Value is some kind of number (Value::Integer or Value::Float).
+    /// Returns true if Value is some kind of number (Value::Integer,
+    /// Value::Float or Value::Quantity).  A Value::Range is not a
+    /// number, see `is_range`.
     pub fn is_number(&self) -> bool {
         match self {
-            Value::Integer(_) | Value::Float(_) => true,
+            Value::Integer(_) | Value::Float(_, _) | Value::Quantity(_, _) => true,
             _ => false
         }
     }
@@ -70,12 +270,225 @@ impl Value {
     /// Returns true if Value is a Value::Float
     pub fn is_float(&self) -> bool {
         match self {
-            Value::Float(_) => true,
+            Value::Float(_, _) => true,
             _ => false
         }
     }
+
+    /// Returns true if Value is some kind of temporal value
+    /// (Value::Date, Value::DateTime or Value::Time).
+    pub fn is_temporal(&self) -> bool {
+        matches!(self, Value::Date(_) | Value::DateTime(_) | Value::Time(_))
+    }
+
+    /// Returns true if Value is a reference to another memo
+    /// (Value::Ref or Value::RefById).
+    pub fn is_ref(&self) -> bool {
+        matches!(self, Value::Ref(_, _) | Value::RefById(_))
+    }
+
+    /// Returns true if Value is a number with a unit (Value::Quantity).
+    pub fn is_quantity(&self) -> bool {
+        matches!(self, Value::Quantity(_, _))
+    }
+
+    /// Returns true if Value is an interval of two numbers (Value::Range).
+    pub fn is_range(&self) -> bool {
+        matches!(self, Value::Range(_, _, _))
+    }
+
+    /// Returns the bounds of a Value::Range.  If only one bound has a
+    /// unit, e.g. `1.8 - 2.1 g/cm³`, the unit applies to both bounds.
+    pub fn bounds(&self) -> Option<(Value, Value)> {
+        let (from, to) = match self {
+            Value::Range(from, to, _) => (from.as_ref(), to.as_ref()),
+            _ => return None
+        };
+        let with_unit = |bound: &Value, other: &Value| match other {
+            Value::Quantity(_, unit) if !bound.is_quantity() =>
+                Value::Quantity(Box::new(bound.clone()), unit.clone()),
+            _ => bound.clone()
+        };
+        Some((with_unit(from, to), with_unit(to, from)))
+    }
+
+    /// Returns the standard uncertainty of a number written as e.g.
+    /// `4.002602(2)`, which is 0.000002, or None if the number has
+    /// no uncertainty.
+    pub fn uncertainty(&self) -> Option<f64> {
+        match self {
+            Value::Float(_, lexeme) => {
+                let (mantissa, digits, exponent) = split_uncertainty(lexeme)?;
+                let decimals = mantissa.find('.').map_or(0, |pos| mantissa.len() - pos - 1);
+                let exponent = match exponent {
+                    "" => 0,
+                    e => e[1..].parse::<i32>().ok()?
+                };
+                let u = digits.parse::<f64>().ok()?;
+                Some(u * 10f64.powi(exponent - decimals as i32))
+            },
+            Value::Quantity(number, _) => number.uncertainty(),
+            _ => None
+        }
+    }
+
+    /// Returns true if the number is marked as approximate, e.g.
+    /// `~1.8`, or if it is the mass number of the most stable
+    /// isotope, e.g. `[209]`.
+    pub fn is_approximate(&self) -> bool {
+        match self {
+            Value::Float(_, lexeme) => lexeme.starts_with(['~', '[']),
+            Value::Quantity(number, _) => number.is_approximate(),
+            _ => false
+        }
+    }
+
+    /// Returns number and unit of a Value::Quantity, or None if the
+    /// Value is not a quantity.
+    pub fn quantity(&self) -> Option<(f64, Unit)> {
+        match self {
+            Value::Quantity(number, unit) =>
+                Some((f64::try_from(number.as_ref()).ok()?, Unit::parse(unit)?)),
+            _ => None
+        }
+    }
+}
+
+// Split a number with uncertainty such as `6.02214076(10)e23` into
+// mantissa, uncertainty digits and exponent (including `e`).
+fn split_uncertainty(s: &str) -> Option<(&str, &str, &str)> {
+    let open = s.find('(')?;
+    let close = s.find(')')?;
+    let digits = s.get(open + 1..close)?;
+    if digits.is_empty() || !digits.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    Some((&s[..open], digits, &s[close + 1..]))
+}
+
+impl Value {
+    // Position of the kind of value in the ordering of values.
+    fn rank(&self) -> u8 {
+        match self {
+            Value::Bool(_) => 0,
+            Value::Integer(_) | Value::Float(_, _) | Value::Quantity(_, _) => 1,
+            Value::Range(_, _, _) => 2,
+            Value::Date(_) | Value::DateTime(_) => 3,
+            Value::Time(_) => 4,
+            Value::Text(_) | Value::MultiLineText(_, _) => 5,
+            Value::Ref(_, _) => 6,
+            Value::RefById(_) => 7
+        }
+    }
+
+    // Numerical value used for sorting, quantities are converted
+    // into SI base units.
+    fn sort_number(&self) -> f64 {
+        match self {
+            Value::Integer(n) => *n as f64,
+            Value::Float(x, _) => *x,
+            Value::Quantity(number, unit) => {
+                let factor = Unit::parse(unit).map_or(1.0, |unit| unit.factor);
+                number.sort_number() * factor
+            },
+            _ => 0.0
+        }
+    }
+}
+
+// Split text into runs of digits and non-digits.
+fn natural_chunks(s: &str) -> impl Iterator<Item=&str> {
+    let mut rest = s;
+    std::iter::from_fn(move || {
+        let first = rest.chars().next()?;
+        let pos = rest.find(|c: char| c.is_ascii_digit() != first.is_ascii_digit())
+            .unwrap_or(rest.len());
+        let (chunk, tail) = rest.split_at(pos);
+        rest = tail;
+        Some(chunk)
+    })
+}
+
+// Compare text in natural order: runs of digits are compared by
+// their numerical value and letters are compared case-insensitively.
+fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let mut a_chunks = natural_chunks(a);
+    let mut b_chunks = natural_chunks(b);
+    loop {
+        let ordering = match (a_chunks.next(), b_chunks.next()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) if x.starts_with(|c: char| c.is_ascii_digit())
+                && y.starts_with(|c: char| c.is_ascii_digit()) => {
+                let x = x.trim_start_matches('0');
+                let y = y.trim_start_matches('0');
+                x.len().cmp(&y.len()).then_with(|| x.cmp(y))
+            },
+            (Some(x), Some(y)) => x.to_lowercase().cmp(&y.to_lowercase())
+        };
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+}
+
+impl Ord for Value {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // first compare by the natural order, then by the exact
+        // notation, so that only identical values are equal
+        match (self, other) {
+            (Value::Text(a), Value::Text(b)) =>
+                natural_cmp(a, b).then_with(|| a.cmp(b)),
+            (Value::MultiLineText(a, sep_a), Value::MultiLineText(b, sep_b)) =>
+                natural_cmp(a, b).then_with(|| a.cmp(b)).then_with(|| sep_a.cmp(sep_b)),
+            (Value::Text(a), Value::MultiLineText(b, _)) =>
+                natural_cmp(a, b).then_with(|| a.cmp(b)).then(Ordering::Less),
+            (Value::MultiLineText(a, _), Value::Text(b)) =>
+                natural_cmp(a, b).then_with(|| a.cmp(b)).then(Ordering::Greater),
+            (Value::Integer(a), Value::Integer(b)) => a.cmp(b),
+            (Value::Float(a, lexeme_a), Value::Float(b, lexeme_b)) =>
+                a.total_cmp(b).then_with(|| lexeme_a.cmp(lexeme_b)),
+            (Value::Quantity(a, unit_a), Value::Quantity(b, unit_b)) =>
+                self.sort_number().total_cmp(&other.sort_number())
+                    .then_with(|| unit_a.cmp(unit_b))
+                    .then_with(|| a.cmp(b)),
+            (Value::Bool(a), Value::Bool(b)) => a.cmp(b),
+            (Value::Date(a), Value::Date(b)) => a.cmp(b),
+            (Value::DateTime(a), Value::DateTime(b)) => a.cmp(b),
+            (Value::Date(a), Value::DateTime(b)) => a.cmp(&b.date).then(Ordering::Less),
+            (Value::DateTime(a), Value::Date(b)) => a.date.cmp(b).then(Ordering::Greater),
+            (Value::Time(a), Value::Time(b)) => a.cmp(b),
+            (Value::Ref(collection_a, title_a), Value::Ref(collection_b, title_b)) =>
+                collection_a.cmp(collection_b)
+                    .then_with(|| natural_cmp(title_a, title_b))
+                    .then_with(|| title_a.cmp(title_b)),
+            (Value::RefById(a), Value::RefById(b)) => a.cmp(b),
+            (Value::Range(from_a, to_a, sep_a), Value::Range(from_b, to_b, sep_b)) =>
+                from_a.cmp(from_b).then_with(|| to_a.cmp(to_b)).then_with(|| sep_a.cmp(sep_b)),
+            // different kinds of numbers, e.g. Integer and Float
+            _ if self.rank() == 1 && other.rank() == 1 => {
+                let kind = |value: &Value| match value {
+                    Value::Integer(_) => 0,
+                    Value::Float(_, _) => 1,
+                    _ => 2
+                };
+                self.sort_number().total_cmp(&other.sort_number())
+                    .then_with(|| kind(self).cmp(&kind(other)))
+            },
+            _ => self.rank().cmp(&other.rank())
+        }
+    }
+}
+
+impl PartialOrd for Value {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
 }
 
+impl Eq for Value {}
+
 impl std::fmt::Display for Value {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -84,8 +497,15 @@ impl std::fmt::Display for Value {
             // Value::MultiLineText(text, sep) =>
             //     write!(f, "<<{}\n{}\n{}", sep, text, sep),
             Value::Integer(n) => write!(f, "{}", n),
-            Value::Float(x) => write!(f, "{}", x),
-            Value::Bool(b) => write!(f, "{}", b)
+            Value::Float(_, lexeme) => write!(f, "{}", lexeme),
+            Value::Bool(b) => write!(f, "{}", b),
+            Value::Date(date) => write!(f, "{}", date),
+            Value::DateTime(dt) => write!(f, "{}", dt),
+            Value::Time(time) => write!(f, "{}", time),
+            Value::Ref(collection, title) => write!(f, "@{} {}", collection, title),
+            Value::RefById(id) => write!(f, "@#{}", id),
+            Value::Quantity(number, unit) => write!(f, "{} {}", number, unit),
+            Value::Range(from, to, sep) => write!(f, "{}{}{}", from, sep, to)
         }
     }
 }
@@ -98,8 +518,15 @@ impl From<Value> for String {
             Value::Text(text) => format!("{}", text),
             Value::MultiLineText(text, _sep) => format!("{}\n", text),
             Value::Integer(n) => format!("{}", n),
-            Value::Float(x) => format!("{}", x),
-            Value::Bool(b) => format!("{}", b)
+            Value::Float(_, lexeme) => lexeme,
+            Value::Bool(b) => format!("{}", b),
+            Value::Date(date) => date.to_string(),
+            Value::DateTime(dt) => dt.to_string(),
+            Value::Time(time) => time.to_string(),
+            Value::Ref(collection, title) => format!("@{} {}", collection, title),
+            Value::RefById(id) => format!("@#{}", id),
+            Value::Quantity(number, unit) => format!("{} {}", number, unit),
+            Value::Range(from, to, sep) => format!("{}{}{}", from, sep, to)
         }
     }
 }
@@ -127,13 +554,28 @@ impl From<String> for Value {
 
 impl From<i32> for Value {
     fn from(n: i32) -> Value {
+        Value::Integer(n as i64)
+    }
+}
+
+impl From<i64> for Value {
+    fn from(n: i64) -> Value {
         Value::Integer(n)
     }
 }
 
 impl From<f32> for Value {
     fn from(x: f32) -> Value {
-        Value::Float(x)
+        // use the shortest notation of the f32, so that e.g. 1.008
+        // does not become 1.00800001621246337890625
+        let lexeme = x.to_string();
+        Value::Float(lexeme.parse::<f64>().unwrap_or(x as f64), lexeme)
+    }
+}
+
+impl From<f64> for Value {
+    fn from(x: f64) -> Value {
+        Value::Float(x, x.to_string())
     }
 }
 
@@ -143,15 +585,34 @@ impl From<bool> for Value {
     }
 }
 
-impl <'a> TryFrom<&'a Value> for f32 {
+impl From<Date> for Value {
+    fn from(date: Date) -> Value {
+        Value::Date(date)
+    }
+}
+
+impl From<DateTime> for Value {
+    fn from(dt: DateTime) -> Value {
+        Value::DateTime(dt)
+    }
+}
+
+impl From<Time> for Value {
+    fn from(time: Time) -> Value {
+        Value::Time(time)
+    }
+}
+
+impl <'a> TryFrom<&'a Value> for f64 {
     type Error = &'static str;
     
     fn try_from(value: &'a Value) -> Result<Self, Self::Error> {
         match value {
-            Value::Float(x) => Ok(x.clone()),
-            Value::Integer(x) => Ok(x.clone() as f32),
+            Value::Float(x, _) => Ok(*x),
+            Value::Integer(x) => Ok(*x as f64),
+            Value::Quantity(number, _) => f64::try_from(number.as_ref()),
             Value::Text(text) => {
-                match text.parse::<f32>() {
+                match text.parse::<f64>() {
                     Ok(x) => Ok(x),
                     _ => Err("Value::Text cannot be parsed as float")
                 }
@@ -161,3 +622,202 @@ impl <'a> TryFrom<&'a Value> for f32 {
     }
 }
 
+impl <'a> TryFrom<&'a Value> for Date {
+    type Error = &'static str;
+
+    fn try_from(value: &'a Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::Date(date) => Ok(*date),
+            Value::DateTime(dt) => Ok(dt.date),
+            // a year on its own, e.g. `1808`, is inferred as Integer
+            Value::Integer(year) if (1000..=9999).contains(year) =>
+                Ok(Date { year: *year as i32, month: None, day: None }),
+            Value::Text(text) => {
+                match text.parse::<Date>() {
+                    Ok(date) => Ok(date),
+                    _ => Err("Value::Text cannot be parsed as date")
+                }
+            },
+            _ => Err("Value is neither Value::Date, Value::DateTime nor a year")
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn infer_temporal() {
+        assert_eq!(Value::infer("1892-01-03"),
+                   Value::Date(Date::from_ymd(1892, 1, 3).unwrap()));
+        assert!(Value::infer("1808-06").is_temporal());
+        assert!(Value::infer("2021-01-24T14:30").is_temporal());
+        assert!(Value::infer("14:30").is_temporal());
+
+        // a single year is just a number
+        assert_eq!(Value::infer("1766"), Value::Integer(1766));
+        assert_eq!(Value::infer("2021-02-30"), Value::Text("2021-02-30".into()));
+        assert_eq!(Value::infer("Henry Cavendish"), Value::Text("Henry Cavendish".into()));
+    }
+
+    #[test]
+    fn infer_number() {
+        assert_eq!(Value::infer("1"), Value::Integer(1));
+        assert_eq!(Value::infer("-42"), Value::Integer(-42));
+        assert_eq!(Value::infer("1.008"), Value::from(1.008));
+        assert_eq!(Value::infer("-0.5"), Value::from(-0.5));
+        assert_eq!(Value::infer("9007199254740993"), Value::Integer(9007199254740993));
+
+        for s in &["007", "+1", "1.", ".5", "1e", "1.5e+", "1s^1", "1-2", "inf", "NaN"] {
+            assert_eq!(Value::infer(s), Value::Text(s.to_string()));
+        }
+    }
+
+    #[test]
+    fn keep_lexeme() {
+        for s in &["118.710", "127.60", "9.0121831", "6.02214076e23", "1E-3", "-0.50"] {
+            let value = Value::infer(s);
+            assert!(value.is_float());
+            assert_eq!(value.to_string(), *s);
+        }
+
+        let amu = Value::infer("9.0121831");
+        assert_eq!(f64::try_from(&amu), Ok(9.0121831));
+        assert_eq!(Value::infer("127.60"), Value::Float(127.6, "127.60".into()));
+        assert_ne!(Value::infer("127.60"), Value::infer("127.6"));
+        assert_eq!(Value::infer("127.60").to_string(), "127.60");
+    }
+
+    #[test]
+    fn infer_uncertainty() {
+        let amu = Value::infer("4.002602(2)");
+        assert_eq!(amu, Value::Float(4.002602, "4.002602(2)".into()));
+        assert_eq!(amu.to_string(), "4.002602(2)");
+        assert!((amu.uncertainty().unwrap() - 0.000002).abs() < 1e-12);
+        assert!(!amu.is_approximate());
+
+        let avogadro = Value::infer("6.02214076(10)e23");
+        assert_eq!(f64::try_from(&avogadro), Ok(6.02214076e23));
+        assert!((avogadro.uncertainty().unwrap() / 1e16 - 1.0).abs() < 1e-9);
+        assert_eq!(Value::infer("12(3)").uncertainty(), Some(3.0));
+
+        for s in &["[209]", "~1.8", "~-0.5"] {
+            let value = Value::infer(s);
+            assert!(value.is_float() && value.is_approximate());
+            assert_eq!(value.uncertainty(), None);
+            assert_eq!(value.to_string(), *s);
+        }
+        assert_eq!(f64::try_from(&Value::infer("[209]")), Ok(209.0));
+
+        let density = Value::infer("~1.8 g/cm³");
+        assert!(density.is_quantity() && density.is_approximate());
+        assert_eq!(Value::infer("2.35(5) T").uncertainty().map(|u| (u * 100.0).round()), Some(5.0));
+
+        assert_eq!(Value::from(1.8).uncertainty(), None);
+        assert!(!Value::from(1.8).is_approximate());
+        for s in &["1.2()", "1.2(a)", "(2)", "[He]", "~", "[]", "~abc"] {
+            assert_eq!(Value::infer(s), Value::Text(s.to_string()));
+        }
+    }
+
+    #[test]
+    fn infer_quantity() {
+        let value = Value::infer("2.35 T");
+        assert_eq!(value, Value::Quantity(Box::new(Value::from(2.35)), "T".into()));
+        assert_eq!(value.to_string(), "2.35 T");
+        assert_eq!(f64::try_from(&value), Ok(2.35));
+        assert_eq!(value.quantity(), Some((2.35, Unit::parse("T").unwrap())));
+
+        assert!(Value::infer("7.87 g/cm³").is_quantity());
+        assert!(Value::infer("1 mol").is_quantity());
+        assert_eq!(Value::infer("12 Monkeys"), Value::Text("12 Monkeys".into()));
+        assert_eq!(Value::from(2.35).quantity(), None);
+    }
+
+    #[test]
+    fn infer_range() {
+        let range = |from: Value, to: Value| Value::Range(Box::new(from), Box::new(to), " - ".into());
+        let value = Value::infer("1.8 - 2.1");
+        assert_eq!(value, range(Value::from(1.8), Value::from(2.1)));
+        assert_eq!(value.to_string(), "1.8 - 2.1");
+        assert!(f64::try_from(&value).is_err());
+
+        // the separator is kept, so that the range is written back unchanged
+        assert_ne!(Value::infer("1.8–2.1"), value);
+        for s in &["1.8–2.1", "1.8 – 2.1", "1.8 –2.1 g/cm³"] {
+            assert_eq!(Value::infer(s).to_string(), *s);
+        }
+        assert_eq!(Value::infer("-5 - -3"), range(Value::Integer(-5), Value::Integer(-3)));
+        let density = Value::infer("1.8 - 2.1 g/cm³");
+        assert_eq!(density, range(Value::from(1.8), Value::infer("2.1 g/cm³")));
+        assert_eq!(density.bounds(), Some((Value::infer("1.8 g/cm³"), Value::infer("2.1 g/cm³"))));
+        assert_eq!(value.bounds(), Some((Value::from(1.8), Value::from(2.1))));
+
+        assert!(!Value::infer("1808-06").is_range());
+        assert!(!Value::infer("Cavendish - Lavoisier").is_range());
+    }
+
+    #[test]
+    fn order_values() {
+        let mut values: Vec<Value> = [
+            "item 10", "1.5 - 2", "Item 9", "2021-01-24T14:30", "2.35 T", "false",
+            "2021-01-24", "-3", "@author Tolkien", "14:30", "1.008", "true", "100 mT",
+            "1808", "item 9"
+        ].iter().map(|s| Value::infer(s)).collect();
+        values.sort();
+        let sorted: Vec<String> = values.iter().map(|value| value.to_string()).collect();
+        assert_eq!(sorted, vec!(
+            "false", "true", "-3", "100 mT", "1.008", "2.35 T", "1808", "1.5 - 2",
+            "2021-01-24", "2021-01-24T14:30", "14:30", "Item 9", "item 9", "item 10",
+            "@author Tolkien"
+        ));
+
+        assert!(Value::Integer(2) < Value::from(2.5));
+        assert!(Value::from(2.5) < Value::Integer(3));
+        assert!(Value::infer("2000 kg/m³") < Value::infer("7.87 g/cm³"));
+        assert!(Value::infer("127.6") < Value::infer("127.60"));
+        assert!(Value::infer("127.60") < Value::infer("127.7"));
+        assert_eq!(Value::infer("x2").cmp(&Value::infer("x02")), Ordering::Greater);
+        assert!(Value::infer("2021") < Value::infer("2021-01"));
+        assert_ne!(Value::Integer(1), Value::from(1.0));
+        assert_eq!(Value::infer("1.008"), Value::infer("1.008"));
+    }
+
+    #[test]
+    fn infer_bool() {
+        assert_eq!(Value::infer("true"), Value::Bool(true));
+        assert_eq!(Value::infer("yes"), Value::Bool(true));
+        assert_eq!(Value::infer("false"), Value::Bool(false));
+        assert_eq!(Value::infer("no"), Value::Bool(false));
+        assert_eq!(Value::infer("True"), Value::Text("True".into()));
+    }
+
+    #[test]
+    fn infer_ref() {
+        assert_eq!(Value::infer("@author J.R.R. Tolkien"),
+                   Value::Ref("author".into(), "J.R.R. Tolkien".into()));
+        assert_eq!(Value::infer("@mr:filter noble gases"),
+                   Value::Ref("mr:filter".into(), "noble gases".into()));
+        assert_eq!(Value::infer("@author J.R.R. Tolkien").to_string(),
+                   "@author J.R.R. Tolkien");
+
+        assert_eq!(Value::infer("@#tolkien"), Value::RefById("tolkien".into()));
+        assert_eq!(Value::infer("@#a1b2-c3").to_string(), "@#a1b2-c3");
+        assert!(Value::infer("@#tolkien").is_ref());
+        assert!(!Value::infer("@#").is_ref());
+        assert!(!Value::infer("@#J.R.R. Tolkien").is_ref());
+
+        assert!(!Value::infer("@author").is_ref());
+        assert!(!Value::infer("@1st place").is_ref());
+        assert!(!Value::infer("nacl42@github").is_ref());
+    }
+
+    #[test]
+    fn display_temporal() {
+        for s in &["1892-01-03", "1808-06", "2021-01-24T14:30:15Z", "14:30"] {
+            assert_eq!(Value::infer(s).to_string(), *s);
+        }
+    }
+}
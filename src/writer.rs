@@ -0,0 +1,346 @@
+//! Writer to turn memos back into `.mr` text.
+//!
+//! The output can be read in again by the parser, which results in
+//! equal memos.  Each node is written on its own line, followed by
+//! its attributes:
+//!
+//! ```text
+//! @element Fe
+//! .amu 55.845
+//! .discovery Cavendish
+//! +date 1808-06-30
+//! ```
+//!
+//! Multi-line values are written with their separator, e.g. `.doc<<EOF`.
+//! If the separator would clash with the text, another one is chosen.
+//! Single-line values that could be mistaken for other syntax, e.g. a
+//! value starting with `,`, are written in the same explicit form.
+//!
+//! If a text value would be read in as some other kind of value, e.g.
+//! the text `42`, the node gets the attribute `+type text`.  Such
+//! attribute values are quoted and written inline instead, e.g.
+//! `.code X {+number "42"}`.
+//!
+//! Comments are written before the memo or node they belong to.
+//! Trailing comments of a memo are written after it, separated by an
+//! empty line.
+//! Nodes and attributes inherited from a prototype are not written.
+//!
+//! There are some limitations imposed by the file format: Leading and
+//! trailing whitespace of values is not preserved, and attribute
+//! values are always single-line values.
+//!
+
+use std::fmt::{self, Write};
+
+use crate::{Key, Memo, Node, Value};
+
+type Attr = (Key, Value);
+
+// Returns true if the given separator may be used as delimiter of
+// the given multi-line text, i.e. if no line starts with it.
+fn is_valid_sep(text: &str, sep: &str) -> bool {
+    !sep.is_empty()
+        && !sep.contains(char::is_whitespace)
+        && !text.lines().any(|line| line.trim_start().starts_with(sep))
+}
+
+// Returns the separator to use for the given multi-line text,
+// preferably `sep` or otherwise `EOF`, `EOF1`, `EOF2` etc.
+fn separator(text: &str, sep: &str) -> String {
+    if is_valid_sep(text, sep) {
+        return sep.to_string();
+    }
+    std::iter::once("EOF".to_string())
+        .chain((1..).map(|n| format!("EOF{}", n)))
+        .find(|sep| is_valid_sep(text, sep))
+        .unwrap()
+}
+
+// Returns true if the given single-line value must be written in the
+// explicit form, because the parser would otherwise read it as a
+// multi-value node, as a multi-line value or as inline attributes.
+fn needs_separator(text: &str) -> bool {
+    text.starts_with([',', '|', ';'])
+        || text.starts_with("<<")
+        || (text.contains("{+") && text.ends_with('}'))
+}
+
+// Returns true if the given attribute value must be quoted, because
+// it would otherwise be read as another kind of value, e.g. the text
+// `42`, or as the end of an inline attribute.
+fn needs_quotes(value: &Value) -> bool {
+    matches!(value, Value::Text(text) if text.contains('}') || Value::infer(text) != *value)
+}
+
+// Writes the given attributes inline, e.g. ` {+unit T}`.
+fn write_inline_attrs<W: Write>(w: &mut W, attrs: &[&Attr]) -> fmt::Result {
+    for (key, value) in attrs {
+        match needs_quotes(value) {
+            true => {
+                let quoted = value.to_string().replace('\\', "\\\\").replace('"', "\\\"");
+                write!(w, " {{+{} \"{}\"}}", key, quoted)?
+            },
+            false => write!(w, " {{+{} {}}}", key, value)?
+        }
+    }
+    Ok(())
+}
+
+// Writes the value of a node, prefixed with the key and followed by
+// the given inline attributes, e.g. `.amu 55.845` or `.doc<<EOF`.
+fn write_value<W: Write>(w: &mut W, prefix: &str, key: &str, value: &Value,
+                         inline: &[&Attr]) -> fmt::Result {
+    let (text, sep) = match value {
+        Value::MultiLineText(text, sep) => (text.clone(), Some(separator(text, sep))),
+        _ => {
+            let text = value.to_string();
+            let explicit = text.contains('\n') || needs_separator(&text)
+                || (!inline.is_empty() && text.contains("{+"));
+            let sep = match explicit {
+                true => Some(separator(&text, "EOF")),
+                false => None
+            };
+            (text, sep)
+        }
+    };
+    match sep {
+        Some(sep) => {
+            write!(w, "{}{}<<{}", prefix, key, sep)?;
+            write_inline_attrs(w, inline)?;
+            write!(w, "\n{}\n{}", text, sep)
+        },
+        None if text.is_empty() => {
+            write!(w, "{}{}", prefix, key)?;
+            write_inline_attrs(w, inline)
+        },
+        None => {
+            write!(w, "{}{} {}", prefix, key, text)?;
+            write_inline_attrs(w, inline)
+        }
+    }
+}
+
+// Writes the comments of a node, each followed by a newline.
+fn write_comments<W: Write>(w: &mut W, node: &Node) -> fmt::Result {
+    for comment in node.comments() {
+        writeln!(w, "#{}", comment)?;
+    }
+    Ok(())
+}
+
+// Returns the attributes of a node that are written, split into those
+// written inline and those written one per line.  Attributes up to
+// the last one that must be quoted are written inline, which keeps
+// their order.
+fn split_attrs(node: &Node) -> (Vec<&Attr>, Vec<&Attr>) {
+    let mut attrs: Vec<&Attr> = node.attrs().iter()
+        .filter(|(key, _)| !node.inherited_attrs.contains(key))
+        .collect();
+    let inline = attrs.iter().rposition(|(_, value)| needs_quotes(value)).map_or(0, |n| n + 1);
+    let lines = attrs.split_off(inline);
+    (attrs, lines)
+}
+
+// Writes the given attributes, one per line.
+fn write_attrs<W: Write>(w: &mut W, attrs: &[&Attr]) -> fmt::Result {
+    for (key, value) in attrs {
+        write!(w, "\n+{} {}", key, value)?;
+    }
+    Ok(())
+}
+
+/// Writes a single data node with its comments and attributes,
+/// without a trailing newline.
+pub fn write_node<W: Write>(w: &mut W, node: &Node) -> fmt::Result {
+    write_comments(w, node)?;
+    let (inline, attrs) = split_attrs(node);
+    write_value(w, ".", &node.key, &node.value, &inline)?;
+    // text that looks like another kind of value must be marked
+    if let Value::Text(text) = &node.value {
+        if Value::infer(text) != node.value {
+            write!(w, "\n+type text")?;
+        }
+    }
+    write_attrs(w, &attrs)
+}
+
+/// Writes a memo, i.e. the header node and all data nodes, each
+/// followed by a newline.
+pub fn write_memo<W: Write>(w: &mut W, memo: &Memo) -> fmt::Result {
+    let header = memo.header();
+    write_comments(w, header)?;
+    let (inline, attrs) = split_attrs(header);
+    write_value(w, "@", &header.key, &header.value, &inline)?;
+    write_attrs(w, &attrs)?;
+    writeln!(w)?;
+    for node in memo.data().filter(|node| !node.is_inherited()) {
+        write_node(w, node)?;
+        writeln!(w)?;
+    }
+    if !memo.trailing_comments().is_empty() {
+        writeln!(w)?;
+        for comment in memo.trailing_comments() {
+            writeln!(w, "#{}", comment)?;
+        }
+    }
+    Ok(())
+}
+
+/// Returns the given memos as `.mr` text, separated by empty lines.
+pub fn write_memos(memos: &[Memo]) -> String {
+    let mut output = String::new();
+    for (n, memo) in memos.iter().enumerate() {
+        if n > 0 {
+            output.push('\n');
+        }
+        // writing to a String cannot fail
+        write_memo(&mut output, memo).unwrap();
+    }
+    output
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::read_from_str;
+    use crate::sample::setup_memos;
+    use std::convert::TryFrom;
+
+    fn round_trip(memos: &[Memo]) {
+        let output = write_memos(memos);
+        let result = read_from_str(&output).unwrap();
+        assert_eq!(result, memos, "output was:\n{}", output);
+        assert_eq!(write_memos(&result), output);
+    }
+
+    #[test]
+    fn write_memo() {
+        let memo = Memo::new("element", "Fe")
+            .with(("amu", Value::infer("55.845")))
+            .with(("discovery", "Cavendish"))
+            .with_attr("date", Value::infer("1808-06-30"));
+        assert_eq!(memo.to_string(),
+                   "@element Fe\n.amu 55.845\n.discovery Cavendish\n+date 1808-06-30\n");
+        round_trip(&[memo]);
+    }
+
+    #[test]
+    fn write_special_values() {
+        let memo = Memo::new("test", "special values")
+            .with(("number", Value::Text("42".into())))
+            .with(("separator", ", not a list"))
+            .with(("pipe", "| neither"))
+            .with(("eof", "<<EOF"))
+            .with(("inline", "not an {+attribute}"))
+            .with(("empty", ""))
+            .with(("doc", Value::MultiLineText("first\nsecond".into(), "END".into())))
+            .with(("nested", Value::MultiLineText("first\n  indented\nEOF\nlast".into(), "END".into())))
+            .with(("density", Value::infer("1.8 - 2.1 g/cm³")))
+            .with(("ref", Value::infer("@author J.R.R. Tolkien")))
+            .with(("ref_by_id", Value::infer("@#tolkien")));
+        round_trip(&[memo]);
+
+        let memo = Memo::new("test", "attributes")
+            .with(("number", Value::Text("42".into())))
+            .with_attr("source", "Wikipedia")
+            .with(("doc", "first\nsecond"))
+            .with_attr("lang", "en");
+        round_trip(&[memo]);
+
+        // text attributes that look like other values are quoted
+        let memo = Memo::new("test", "quoted attributes")
+            .with_attr("number", Value::Text("1808".into()))
+            .with(("code", "X"))
+            .with_attr("number", Value::Text("42".into()))
+            .with_attr("quote", "say \"hi\" {here}")
+            .with_attr("source", "Wikipedia")
+            .with(("set", "{+a} and more"))
+            .with_attr("flag", Value::Text("true".into()))
+            .with(("doc", "first\nsecond"))
+            .with_attr("lang", "en")
+            .with_attr("date", Value::Text("2020-01-01".into()))
+            .with_attr("source", "Wikipedia");
+        assert_eq!(memo.to_string(), "@test quoted attributes {+number \"1808\"}\n\
+                                      .code X {+number \"42\"} {+quote \"say \\\"hi\\\" {here}\"}\n+source Wikipedia\n\
+                                      .set<<EOF {+flag \"true\"}\n{+a} and more\nEOF\n\
+                                      .doc<<EOF {+lang en} {+date \"2020-01-01\"}\nfirst\nsecond\nEOF\n+source Wikipedia\n");
+        round_trip(&[memo]);
+
+        // a separator that clashes with the text is replaced
+        let node = Node::new("doc", Value::MultiLineText("first\nEND\nEOF".into(), "END".into()));
+        let mut output = String::new();
+        write_node(&mut output, &node).unwrap();
+        assert_eq!(output, ".doc<<EOF1\nfirst\nEND\nEOF\nEOF1");
+    }
+
+    #[test]
+    fn write_approximate_numbers() {
+        let input = "@element He\n.amu 4.002602(2)\n\n@element Tc\n.amu [97]\n\n@element Po\n.amu ~209\n";
+        let memos = read_from_str(input).unwrap();
+        let amu: Vec<f64> = memos.iter()
+            .map(|memo| f64::try_from(&memo.get("amu").unwrap().value).unwrap())
+            .collect();
+        assert_eq!(amu, vec!(4.002602, 97.0, 209.0));
+        assert_eq!(write_memos(&memos), input);
+        round_trip(&memos);
+    }
+
+    #[test]
+    fn write_header_attrs() {
+        let memo = Memo::new("element", "H")
+            .with_attr("id", "h")
+            .with_attr("source", "Wikipedia")
+            .with(("number", 1));
+        assert_eq!(memo.to_string(), "@element H\n+id h\n+source Wikipedia\n.number 1\n");
+        round_trip(&[memo, Memo::new("element", "He").with_attr("id", "he")]);
+    }
+
+    #[test]
+    fn write_comments() {
+        let mut memo = Memo::new("book", "The Hobbit")
+            .with(Node::new("author", "Tolkien").with_comment(" main author"));
+        memo.header_mut().comments = vec!(" books".into(), "".into());
+        let output = write_memos(&[memo]);
+        assert_eq!(output, "# books\n#\n@book The Hobbit\n# main author\n.author Tolkien\n");
+
+        let memos = read_from_str(&output).unwrap();
+        assert_eq!(memos[0].comments(), [" books", ""]);
+        assert_eq!(memos[0].get("author").unwrap().comments(), [" main author"]);
+
+        // `#` within a value and comments at the end of the file
+        let input = "@template basic\n.body {doc}\n\n#\n\n# second\n@book The Hobbit\n.doc first\n# not a comment\n\n# end\n#\n";
+        let memos = read_from_str(input).unwrap();
+        assert_eq!(memos[0].get("body").unwrap().value, Value::from("{doc}\n\n#"));
+        assert_eq!(memos[1].trailing_comments(), [" end", ""]);
+        let output = write_memos(&memos);
+        assert_eq!(output, "@template basic\n.body<<EOF\n{doc}\n\n#\nEOF\n\n# second\n@book The Hobbit\n\
+                            .doc<<EOF\nfirst\n# not a comment\nEOF\n\n# end\n#\n");
+        let result = read_from_str(&output).unwrap();
+        assert_eq!(result, memos);
+        assert_eq!(result[1].comments(), [" second"]);
+        assert_eq!(result[1].trailing_comments(), [" end", ""]);
+        assert_eq!(write_memos(&result), output);
+    }
+
+    #[test]
+    fn write_samples() {
+        round_trip(&setup_memos());
+    }
+
+    #[test]
+    fn write_data_files() {
+        let dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR"));
+        let files = std::fs::read_dir(dir.join("data")).unwrap()
+            .map(|entry| entry.unwrap().path())
+            .chain(std::iter::once(dir.join("TODO.mr")))
+            .filter(|path| path.extension().is_some_and(|ext| ext == "mr"));
+        for path in files {
+            let input = std::fs::read_to_string(&path).unwrap();
+            let memos = read_from_str(&input).unwrap();
+            assert!(!memos.is_empty(), "no memos in {:?}", path);
+            round_trip(&memos);
+        }
+    }
+}